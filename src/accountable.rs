@@ -0,0 +1,378 @@
+use crate::members::Members;
+use crate::scalar_from_canonical;
+use crate::scalar_random;
+use crate::scalar_zero;
+use crate::Error;
+use crate::PublicKey;
+use crate::Ring;
+use crate::RingHash;
+use crate::SecretKey;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
+use crate::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::MultiscalarMul;
+use curve25519_dalek::traits::VartimeMultiscalarMul;
+use curve25519_dalek::Scalar;
+use rand::Rng;
+use rand_core::CryptoRngCore;
+use serde::Deserialize;
+use serde::Serialize;
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Accountable {
+    pub challenge: [u8; 32],
+    pub response: Vec<[u8; 32]>,
+    pub response_r: Vec<[u8; 32]>,
+    pub ciphertext: [[u8; 32]; 2],
+    pub ring: Ring,
+}
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Opening {
+    pub challenge: [u8; 32],
+    pub response: [u8; 32],
+}
+impl Accountable {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey,
+        mut ring: Ring,
+        opener: &PublicKey,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Accountable, Error> {
+        let secret_index = rng.gen_range(0..=ring.len());
+        ring.0.insert(secret_index, secret.public_key());
+        Accountable::sign_with_index::<Hash>(rng, secret, ring, secret_index, opener, data)
+    }
+    pub fn sign_with_index<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey,
+        ring: Ring,
+        secret_index: usize,
+        opener: &PublicKey,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Accountable, Error> {
+        if secret_index >= ring.len() {
+            return Err(Error::SignerIndexOutOfRange);
+        }
+        if ring[secret_index] != secret.public_key() {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let members = Members::new(ring.chunks(1))?;
+        let x = ring.len();
+        let r = Zeroizing::new(scalar_random(rng));
+        let ciphertext = [
+            *r * RISTRETTO_BASEPOINT_POINT,
+            ring[secret_index].0 + *r * opener.0,
+        ];
+        let transcript =
+            Accountable::transcript::<Hash>(&members.bytes, opener, &ciphertext, data)?;
+        let challenge = |[l, b, d]: [RistrettoPoint; 3]| {
+            let mut transcript = transcript.clone();
+            transcript.append_point(b"L", &l);
+            transcript.append_point(b"B", &b);
+            transcript.append_point(b"D", &d);
+            transcript.challenge_scalar(b"c")
+        };
+        let alpha = Zeroizing::new(scalar_random(rng));
+        let beta = Zeroizing::new(scalar_random(rng));
+        let mut challenges = vec![scalar_zero(); x];
+        let mut response = (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+        let mut response_r = (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+        let mut current_index = (secret_index + 1) % x;
+        challenges[current_index] = challenge([
+            *alpha * RISTRETTO_BASEPOINT_POINT,
+            *beta * RISTRETTO_BASEPOINT_POINT,
+            *beta * opener.0,
+        ]);
+        while current_index != secret_index {
+            let next_index = (current_index + 1) % x;
+            challenges[next_index] = challenge(commitments(
+                response[current_index],
+                response_r[current_index],
+                challenges[current_index],
+                ring[current_index].0,
+                opener.0,
+                &ciphertext,
+            ));
+            current_index = next_index;
+        }
+        response[secret_index] = *alpha - *Zeroizing::new(challenges[secret_index] * secret.0);
+        response_r[secret_index] = *beta - *Zeroizing::new(challenges[secret_index] * *r);
+        Ok(Accountable {
+            challenge: challenges[0].to_bytes(),
+            response: response.iter().map(Scalar::to_bytes).collect(),
+            response_r: response_r.iter().map(Scalar::to_bytes).collect(),
+            ciphertext: [
+                ciphertext[0].compress().to_bytes(),
+                ciphertext[1].compress().to_bytes(),
+            ],
+            ring,
+        })
+    }
+    pub fn verify<Hash: RingHash>(
+        &self,
+        opener: &PublicKey,
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        self.ciphertext::<Hash>(opener, data.as_ref()).map(|_| ())
+    }
+    pub fn open<Hash: RingHash>(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        opener: &SecretKey,
+        data: impl AsRef<[u8]>,
+    ) -> Result<(PublicKey, Opening), Error> {
+        let ciphertext = self.ciphertext::<Hash>(&opener.public_key(), data.as_ref())?;
+        let signer = PublicKey::from(ciphertext[1] - opener.0 * ciphertext[0]);
+        let k = Zeroizing::new(scalar_random(rng));
+        let challenge = Opening::challenge::<Hash>(
+            &opener.public_key(),
+            &ciphertext,
+            &signer,
+            [*k * RISTRETTO_BASEPOINT_POINT, *k * ciphertext[0]],
+        )?;
+        Ok((
+            signer,
+            Opening {
+                challenge: challenge.to_bytes(),
+                response: (*k - *Zeroizing::new(challenge * opener.0)).to_bytes(),
+            },
+        ))
+    }
+    fn ciphertext<Hash: RingHash>(
+        &self,
+        opener: &PublicKey,
+        data: &[u8],
+    ) -> Result<[RistrettoPoint; 2], Error> {
+        let members = Members::new(self.ring.chunks(1))?;
+        if self.response.len() != self.ring.len() || self.response_r.len() != self.ring.len() {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let ciphertext = [
+            crate::point_from_slice(&self.ciphertext[0]).ok_or(Error::InvalidProofPoint)?,
+            crate::point_from_slice(&self.ciphertext[1]).ok_or(Error::InvalidProofPoint)?,
+        ];
+        let challenge_0 =
+            scalar_from_canonical(self.challenge).ok_or(Error::NonCanonicalChallenge)?;
+        let scalars = |response: &[[u8; 32]], offset: usize| {
+            response
+                .iter()
+                .enumerate()
+                .map(|(index, &bytes)| {
+                    scalar_from_canonical(bytes).ok_or(Error::NonCanonicalScalar {
+                        index: offset + index,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let response = scalars(&self.response, 0)?;
+        let response_r = scalars(&self.response_r, self.ring.len())?;
+        let transcript =
+            Accountable::transcript::<Hash>(&members.bytes, opener, &ciphertext, data)?;
+        let mut challenge_1 = challenge_0;
+        for ((response, response_r), key) in response.iter().zip(&response_r).zip(&members.points) {
+            let [l, b, d] = commitments(
+                *response,
+                *response_r,
+                challenge_1,
+                *key,
+                opener.0,
+                &ciphertext,
+            );
+            let mut transcript = transcript.clone();
+            transcript.append_point(b"L", &l);
+            transcript.append_point(b"B", &b);
+            transcript.append_point(b"D", &d);
+            challenge_1 = transcript.challenge_scalar(b"c");
+        }
+        if challenge_0 != challenge_1 {
+            return Err(Error::ChallengeMismatch);
+        }
+        Ok(ciphertext)
+    }
+    fn transcript<Hash: RingHash>(
+        ring: &[[u8; 32]],
+        opener: &PublicKey,
+        ciphertext: &[RistrettoPoint; 2],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript =
+            Transcript::versioned(Version::V2, b"ring-signature/Accountable/v2", b"")?;
+        for key in ring {
+            transcript.append_message(b"ring", key);
+        }
+        transcript.append_message(b"opener", opener.to_bytes());
+        transcript.append_point(b"C_1", &ciphertext[0]);
+        transcript.append_point(b"C_2", &ciphertext[1]);
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+}
+impl Opening {
+    pub fn verify<Hash: RingHash>(
+        &self,
+        signature: &Accountable,
+        opener: &PublicKey,
+        signer: &PublicKey,
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        let ciphertext = signature.ciphertext::<Hash>(opener, data.as_ref())?;
+        if !signature.ring.contains(signer) {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let challenge =
+            scalar_from_canonical(self.challenge).ok_or(Error::NonCanonicalChallenge)?;
+        let response =
+            scalar_from_canonical(self.response).ok_or(Error::NonCanonicalScalar { index: 0 })?;
+        let scalars = [response, challenge];
+        let commitments = [
+            RistrettoPoint::vartime_multiscalar_mul(scalars, [RISTRETTO_BASEPOINT_POINT, opener.0]),
+            RistrettoPoint::vartime_multiscalar_mul(
+                scalars,
+                [ciphertext[0], ciphertext[1] - signer.0],
+            ),
+        ];
+        if Opening::challenge::<Hash>(opener, &ciphertext, signer, commitments)? != challenge {
+            return Err(Error::InvalidProof);
+        }
+        Ok(())
+    }
+    fn challenge<Hash: RingHash>(
+        opener: &PublicKey,
+        ciphertext: &[RistrettoPoint; 2],
+        signer: &PublicKey,
+        commitments: [RistrettoPoint; 2],
+    ) -> Result<Scalar, Error> {
+        let mut transcript =
+            Transcript::<Hash>::versioned(Version::V2, b"ring-signature/Accountable/v2/open", b"")?;
+        transcript.append_message(b"opener", opener.to_bytes());
+        transcript.append_point(b"C_1", &ciphertext[0]);
+        transcript.append_point(b"C_2", &ciphertext[1]);
+        transcript.append_message(b"signer", signer.to_bytes());
+        transcript.append_point(b"A", &commitments[0]);
+        transcript.append_point(b"B", &commitments[1]);
+        Ok(transcript.challenge_scalar(b"c"))
+    }
+}
+fn commitments(
+    response: Scalar,
+    response_r: Scalar,
+    challenge: Scalar,
+    key: RistrettoPoint,
+    opener: RistrettoPoint,
+    ciphertext: &[RistrettoPoint; 2],
+) -> [RistrettoPoint; 3] {
+    [
+        RistrettoPoint::multiscalar_mul([response, challenge], [RISTRETTO_BASEPOINT_POINT, key]),
+        RistrettoPoint::multiscalar_mul(
+            [response_r, challenge],
+            [RISTRETTO_BASEPOINT_POINT, ciphertext[0]],
+        ),
+        RistrettoPoint::multiscalar_mul([response_r, challenge], [opener, ciphertext[1] - key]),
+    ]
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_random;
+    use lazy_static::lazy_static;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    const DATA_0: &[u8] = b"hello from";
+    const DATA_1: &str = "zudo";
+    const X: usize = 4;
+    lazy_static! {
+        static ref SECRET: SecretKey = SecretKey::random(&mut OsRng);
+        static ref OPENER_0: SecretKey = SecretKey::random(&mut OsRng);
+        static ref OPENER_1: SecretKey = SecretKey::random(&mut OsRng);
+        static ref RING: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
+    }
+    #[test]
+    fn sign_verify() {
+        let rng = &mut OsRng;
+        let opener = OPENER_0.public_key();
+        for x in [0, 1, 2, 5] {
+            let ring = (0..x).map(|_| point_random(rng)).collect();
+            let a = Accountable::sign::<Sha512>(rng, &SECRET, ring, &opener, DATA_0).unwrap();
+            assert!(a.verify::<Sha512>(&opener, DATA_0).is_ok());
+            assert_eq!(
+                a.verify::<Sha512>(&opener, DATA_1),
+                Err(Error::ChallengeMismatch)
+            );
+            assert_eq!(
+                a.verify::<Sha512>(&OPENER_1.public_key(), DATA_0),
+                Err(Error::ChallengeMismatch)
+            );
+        }
+        assert_eq!(
+            Accountable::sign_with_index::<Sha512>(rng, &SECRET, RING.clone(), 0, &opener, DATA_0),
+            Err(Error::SignerKeyMismatch)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let rng = &mut OsRng;
+        let opener = OPENER_0.public_key();
+        let a = Accountable::sign::<Sha512>(rng, &SECRET, RING.clone(), &opener, DATA_0).unwrap();
+        let mut b = a.clone();
+        b.ciphertext[1] = (point_random(rng)).compress().to_bytes();
+        assert_eq!(
+            b.verify::<Sha512>(&opener, DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        let mut c = a.clone();
+        c.ciphertext[0] = [0xff; 32];
+        assert_eq!(
+            c.verify::<Sha512>(&opener, DATA_0),
+            Err(Error::InvalidProofPoint)
+        );
+        let mut d = a.clone();
+        d.response_r.pop();
+        assert_eq!(
+            d.verify::<Sha512>(&opener, DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut e = a;
+        e.response_r[1] = [0xff; 32];
+        assert_eq!(
+            e.verify::<Sha512>(&opener, DATA_0),
+            Err(Error::NonCanonicalScalar { index: X + 2 })
+        );
+    }
+    #[test]
+    fn open() {
+        let rng = &mut OsRng;
+        let opener = OPENER_0.public_key();
+        let a = Accountable::sign::<Sha512>(rng, &SECRET, RING.clone(), &opener, DATA_0).unwrap();
+        let (signer, opening) = a.open::<Sha512>(rng, &OPENER_0, DATA_0).unwrap();
+        assert_eq!(signer, SECRET.public_key());
+        assert!(opening
+            .verify::<Sha512>(&a, &opener, &signer, DATA_0)
+            .is_ok());
+        assert_eq!(
+            opening.verify::<Sha512>(&a, &opener, &RING[0], DATA_0),
+            Err(Error::InvalidProof)
+        );
+        assert_eq!(
+            opening.verify::<Sha512>(&a, &opener, &OPENER_1.public_key(), DATA_0),
+            Err(Error::SignerKeyMismatch)
+        );
+        assert_eq!(
+            a.open::<Sha512>(rng, &OPENER_1, DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn serde() {
+        let rng = &mut OsRng;
+        let opener = OPENER_0.public_key();
+        let a = Accountable::sign::<Sha512>(rng, &SECRET, RING.clone(), &opener, DATA_0).unwrap();
+        let b: Accountable = bincode::deserialize(&bincode::serialize(&a).unwrap()).unwrap();
+        assert_eq!(a, b);
+        assert!(b.verify::<Sha512>(&opener, DATA_0).is_ok());
+        let (_, c) = a.open::<Sha512>(rng, &OPENER_0, DATA_0).unwrap();
+        let d: Opening = bincode::deserialize(&bincode::serialize(&c).unwrap()).unwrap();
+        assert_eq!(c, d);
+    }
+}
@@ -0,0 +1,622 @@
+use crate::members::Members;
+use crate::one_of_many;
+use crate::one_of_many::Prover;
+use crate::scalar_random;
+use crate::scalar_zero;
+use crate::Error;
+use crate::KeyImage;
+use crate::PublicKey;
+use crate::RingHash;
+use crate::SecretKey;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
+use crate::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::IsIdentity;
+use curve25519_dalek::traits::MultiscalarMul;
+use curve25519_dalek::Scalar;
+use rand_core::CryptoRngCore;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Sha512;
+use std::collections::HashSet;
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Arcturus {
+    pub a: [u8; 32],
+    pub b: [u8; 32],
+    pub c: [u8; 32],
+    pub d: [u8; 32],
+    pub x: Vec<Vec<[u8; 32]>>,
+    pub y: Vec<Vec<[u8; 32]>>,
+    pub e: Vec<Vec<[u8; 32]>>,
+    pub v: Vec<[u8; 32]>,
+    pub f: Vec<Vec<[u8; 32]>>,
+    pub z_a: [u8; 32],
+    pub z_c: [u8; 32],
+    pub z_v: [u8; 32],
+    pub z_0: Vec<[u8; 32]>,
+    pub z_1: Vec<[u8; 32]>,
+    pub z_e: Vec<[u8; 32]>,
+    pub rings: Vec<Vec<PublicKey>>,
+    pub outputs: Vec<[u8; 32]>,
+    pub images: Vec<KeyImage>,
+}
+impl Arcturus {
+    pub fn commit(value: u64, blinding: &Scalar) -> RistrettoPoint {
+        RistrettoPoint::multiscalar_mul(
+            [Scalar::from(value), *blinding],
+            [
+                one_of_many::generator::<Sha512>(b"ring-signature/Arcturus/v2/generator", b"V", 0),
+                RISTRETTO_BASEPOINT_POINT,
+            ],
+        )
+    }
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        inputs: &[(usize, &SecretKey, &Scalar)],
+        rings: Vec<Vec<PublicKey>>,
+        outputs: &[RistrettoPoint],
+        output_blinding: &Scalar,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Arcturus, Error> {
+        Arcturus::sign_with_context::<Hash>(rng, inputs, rings, outputs, output_blinding, b"", data)
+    }
+    pub fn sign_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        inputs: &[(usize, &SecretKey, &Scalar)],
+        rings: Vec<Vec<PublicKey>>,
+        outputs: &[RistrettoPoint],
+        output_blinding: &Scalar,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Arcturus, Error> {
+        if inputs.is_empty() {
+            return Err(Error::EmptySecrets);
+        }
+        let members = Members::new(rings.iter().map(Vec::as_slice))?.hash::<Hash>(Version::V2, 1);
+        if members.width != 2 {
+            return Err(Error::RingWidthMismatch);
+        }
+        for &(index, secret, _) in inputs {
+            if index >= members.len() {
+                return Err(Error::SignerIndexOutOfRange);
+            }
+            if members.row(index)[0] != secret.public_key().0 {
+                return Err(Error::SignerKeyMismatch);
+            }
+        }
+        let images = inputs
+            .iter()
+            .map(|&(index, secret, _)| KeyImage::from(secret.0 * members.hashes(index)[0]))
+            .collect::<Vec<_>>();
+        distinct(&images)?;
+        let blinding = Zeroizing::new(
+            inputs
+                .iter()
+                .map(|(_, _, blinding)| **blinding)
+                .sum::<Scalar>()
+                - output_blinding,
+        );
+        if inputs
+            .iter()
+            .map(|&(index, _, _)| members.row(index)[1])
+            .sum::<RistrettoPoint>()
+            - outputs.iter().sum::<RistrettoPoint>()
+            != *blinding * RISTRETTO_BASEPOINT_POINT
+        {
+            return Err(Error::Unbalanced);
+        }
+        let len = members.len();
+        let m = one_of_many::digits(len);
+        let (h, f_point) = Arcturus::generators::<Hash>(inputs.len() * m);
+        let indices = inputs
+            .iter()
+            .map(|&(index, _, _)| index)
+            .collect::<Vec<_>>();
+        let prover = Prover::new(rng, &h, m, &indices);
+        let mut x = Vec::new();
+        let mut y = Vec::new();
+        let mut e = Vec::new();
+        let mut z_0 = Zeroizing::new(Vec::new());
+        let mut z_1 = Zeroizing::new(Vec::new());
+        let mut e_blinding = Zeroizing::new(Vec::new());
+        for (u, &(_, secret, _)) in inputs.iter().enumerate() {
+            let rho = Zeroizing::new((0..m).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+            let tau = Zeroizing::new((0..m).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+            x.push(one_of_many::compress(&prover.mix(
+                &[u],
+                &rho,
+                |k| members.row(k % len)[0],
+                RISTRETTO_BASEPOINT_POINT,
+            )));
+            y.push(one_of_many::compress(&prover.mix(
+                &[u],
+                &tau,
+                |k| members.hashes(k % len)[0],
+                images[u].0,
+            )));
+            z_0.push(
+                rho.iter()
+                    .map(|rho| -rho)
+                    .chain([secret.0])
+                    .collect::<Vec<_>>(),
+            );
+            z_1.push(
+                tau.iter()
+                    .map(|tau| -tau)
+                    .chain([secret.0.invert()])
+                    .collect::<Vec<_>>(),
+            );
+            let product = Zeroizing::new(one_of_many::poly_mul(&z_0[u], &z_1[u]));
+            e_blinding.push((0..2 * m).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+            e.push(
+                (0..2 * m)
+                    .map(|d| {
+                        RistrettoPoint::multiscalar_mul(
+                            [product[d], e_blinding[u][d]],
+                            [RISTRETTO_BASEPOINT_POINT, f_point],
+                        )
+                        .compress()
+                        .to_bytes()
+                    })
+                    .collect(),
+            );
+        }
+        let omega = Zeroizing::new((0..m).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+        let v = prover.mix(
+            &(0..inputs.len()).collect::<Vec<_>>(),
+            &omega,
+            |k| members.row(k % len)[1],
+            RISTRETTO_BASEPOINT_POINT,
+        );
+        let z_v = Zeroizing::new(
+            omega
+                .iter()
+                .map(|omega| -omega)
+                .chain([*blinding])
+                .collect::<Vec<_>>(),
+        );
+        let mut arcturus = Arcturus {
+            a: prover.commitments[0].compress().to_bytes(),
+            b: prover.commitments[1].compress().to_bytes(),
+            c: prover.commitments[2].compress().to_bytes(),
+            d: prover.commitments[3].compress().to_bytes(),
+            x,
+            y,
+            e,
+            v: one_of_many::compress(&v),
+            f: Vec::new(),
+            z_a: [0; 32],
+            z_c: [0; 32],
+            z_v: [0; 32],
+            z_0: Vec::new(),
+            z_1: Vec::new(),
+            z_e: Vec::new(),
+            rings,
+            outputs: one_of_many::compress(outputs),
+            images,
+        };
+        let xi = Arcturus::transcript::<Hash>(context, &members.bytes, &arcturus, data)?
+            .challenge_scalar(b"xi");
+        (arcturus.f, arcturus.z_a, arcturus.z_c) = prover.respond(xi);
+        arcturus.z_v = one_of_many::poly_eval(&z_v, xi).to_bytes();
+        arcturus.z_0 = z_0
+            .iter()
+            .map(|z_0| one_of_many::poly_eval(z_0, xi).to_bytes())
+            .collect();
+        arcturus.z_1 = z_1
+            .iter()
+            .map(|z_1| one_of_many::poly_eval(z_1, xi).to_bytes())
+            .collect();
+        arcturus.z_e = e_blinding
+            .iter()
+            .map(|e_blinding| one_of_many::poly_eval(e_blinding, xi).to_bytes())
+            .collect();
+        Ok(arcturus)
+    }
+    pub fn verify<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(b"", data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if self.images.is_empty() {
+            return Err(Error::ImageCountMismatch);
+        }
+        if let Some(index) = self.images.iter().position(|image| image.0.is_identity()) {
+            return Err(Error::InvalidKeyImage { index });
+        }
+        distinct(&self.images)?;
+        let members =
+            Members::new(self.rings.iter().map(Vec::as_slice))?.hash::<Hash>(Version::V2, 1);
+        if members.width != 2 {
+            return Err(Error::RingWidthMismatch);
+        }
+        let inputs = self.images.len();
+        let len = members.len();
+        let m = one_of_many::digits(len);
+        if [&self.x, &self.y, &self.e]
+            .iter()
+            .any(|points| points.len() != inputs)
+            || [&self.z_0, &self.z_1, &self.z_e]
+                .iter()
+                .any(|scalars| scalars.len() != inputs)
+            || self.x.iter().chain(&self.y).any(|points| points.len() != m)
+            || self.e.iter().any(|e| e.len() != 2 * m)
+            || self.v.len() != m
+            || self.f.len() != inputs * m
+            || self.f.iter().any(|f_j| f_j.len() != one_of_many::N - 1)
+        {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let bits = [
+            one_of_many::point(&self.a)?,
+            one_of_many::point(&self.b)?,
+            one_of_many::point(&self.c)?,
+            one_of_many::point(&self.d)?,
+        ];
+        let x = self
+            .x
+            .iter()
+            .map(|x| one_of_many::points(x))
+            .collect::<Result<Vec<_>, _>>()?;
+        let y = self
+            .y
+            .iter()
+            .map(|y| one_of_many::points(y))
+            .collect::<Result<Vec<_>, _>>()?;
+        let e = self
+            .e
+            .iter()
+            .map(|e| one_of_many::points(e))
+            .collect::<Result<Vec<_>, _>>()?;
+        let v = one_of_many::points(&self.v)?;
+        let outputs = one_of_many::points(&self.outputs)?;
+        let z_a = one_of_many::scalar(0, self.z_a)?;
+        let z_c = one_of_many::scalar(1, self.z_c)?;
+        let z_v = one_of_many::scalar(2, self.z_v)?;
+        let responses = |offset: usize, scalars: &[[u8; 32]]| {
+            scalars
+                .iter()
+                .enumerate()
+                .map(|(u, &bytes)| one_of_many::scalar(offset + 3 * u, bytes))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let z_0 = responses(3, &self.z_0)?;
+        let z_1 = responses(4, &self.z_1)?;
+        let z_e = responses(5, &self.z_e)?;
+        let xi = Arcturus::transcript::<Hash>(context, &members.bytes, self, data)?
+            .challenge_scalar(b"xi");
+        let f = one_of_many::expand(&self.f, xi, 3 + 3 * inputs)?;
+        let (h, f_point) = Arcturus::generators::<Hash>(inputs * m);
+        let column = |col: usize| (0..len).map(|k| members.row(k)[col]).collect::<Vec<_>>();
+        let keys = column(0);
+        let commitments = column(1);
+        let hashes = (0..len).map(|k| members.hashes(k)[0]).collect::<Vec<_>>();
+        let powers = one_of_many::powers(xi, 2 * m + 1);
+        let mut equations = one_of_many::bits_equations(&h, bits, &f, z_a, z_c, xi).to_vec();
+        let mut total = vec![scalar_zero(); len];
+        for (u, ((x, y), e)) in x.iter().zip(&y).zip(e).enumerate() {
+            let weights = one_of_many::weights(&f[u * m..(u + 1) * m], len);
+            equations.push(one_of_many::mix_equation(
+                &weights,
+                &keys,
+                x,
+                xi,
+                z_0[u],
+                RISTRETTO_BASEPOINT_POINT,
+            ));
+            equations.push(one_of_many::mix_equation(
+                &weights,
+                &hashes,
+                y,
+                xi,
+                z_1[u],
+                self.images[u].0,
+            ));
+            equations.push(
+                powers
+                    .iter()
+                    .map(|power| -power)
+                    .zip(e)
+                    .chain([
+                        (z_0[u] * z_1[u] - powers[2 * m], RISTRETTO_BASEPOINT_POINT),
+                        (z_e[u], f_point),
+                    ])
+                    .collect(),
+            );
+            for (total, weight) in total.iter_mut().zip(weights) {
+                *total += weight;
+            }
+        }
+        let mut balance =
+            one_of_many::mix_equation(&total, &commitments, &v, xi, z_v, RISTRETTO_BASEPOINT_POINT);
+        balance.extend(outputs.into_iter().map(|output| (-powers[m], output)));
+        equations.push(balance);
+        if equations.into_iter().all(one_of_many::is_zero) {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+    fn transcript<Hash: RingHash>(
+        context: &[u8],
+        rings: &[[u8; 32]],
+        arcturus: &Arcturus,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript =
+            Transcript::versioned(Version::V2, b"ring-signature/Arcturus/v2", context)?;
+        for key in rings {
+            transcript.append_message(b"ring", key);
+        }
+        for output in &arcturus.outputs {
+            transcript.append_message(b"output", output);
+        }
+        for image in &arcturus.images {
+            transcript.append_message(b"image", image.to_bytes());
+        }
+        transcript.append_message(b"A", arcturus.a);
+        transcript.append_message(b"B", arcturus.b);
+        transcript.append_message(b"C", arcturus.c);
+        transcript.append_message(b"D", arcturus.d);
+        for ((x, y), e) in arcturus.x.iter().zip(&arcturus.y).zip(&arcturus.e) {
+            for x in x {
+                transcript.append_message(b"X", x);
+            }
+            for y in y {
+                transcript.append_message(b"Y", y);
+            }
+            for e in e {
+                transcript.append_message(b"E", e);
+            }
+        }
+        for v in &arcturus.v {
+            transcript.append_message(b"V", v);
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+    fn generators<Hash: RingHash>(count: usize) -> (Vec<RistrettoPoint>, RistrettoPoint) {
+        (
+            one_of_many::generators::<Hash>(b"ring-signature/Arcturus/v2/generator", count),
+            one_of_many::generator::<Hash>(b"ring-signature/Arcturus/v2/generator", b"F", 0),
+        )
+    }
+}
+fn distinct(images: &[KeyImage]) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    if images.iter().all(|image| seen.insert(image.to_bytes())) {
+        Ok(())
+    } else {
+        Err(Error::DuplicateImage)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clsag::CLSAG;
+    use crate::point_random;
+    use crate::scalar_from_canonical;
+    use lazy_static::lazy_static;
+    use rand::seq::index;
+    use rand_core::OsRng;
+    const DATA_0: &[u8] = b"hello from";
+    const DATA_1: &str = "zudo";
+    const X: usize = 6;
+    struct Spend {
+        rings: Vec<Vec<PublicKey>>,
+        secrets: Vec<(usize, SecretKey, Scalar)>,
+        outputs: Vec<RistrettoPoint>,
+        output_blinding: Scalar,
+    }
+    impl Spend {
+        fn random(len: usize, inputs: usize) -> Spend {
+            let rng = &mut OsRng;
+            let mut rings = (0..len)
+                .map(|_| {
+                    vec![
+                        PublicKey::from(point_random(rng)),
+                        PublicKey::from(point_random(rng)),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            let mut total = 0;
+            let secrets = index::sample(rng, len, inputs)
+                .into_iter()
+                .map(|index| {
+                    let secret = SecretKey::random(rng);
+                    let value = rand::Rng::gen_range(rng, 0..1000);
+                    let mask = scalar_random(rng);
+                    rings[index] = vec![
+                        secret.public_key(),
+                        PublicKey::from(Arcturus::commit(value, &mask)),
+                    ];
+                    total += value;
+                    (index, secret, mask)
+                })
+                .collect();
+            let output_blinding = scalar_random(rng);
+            let first = scalar_random(rng);
+            let outputs = vec![
+                Arcturus::commit(total / 3, &first),
+                Arcturus::commit(total - total / 3, &(output_blinding - first)),
+            ];
+            Spend {
+                rings,
+                secrets,
+                outputs,
+                output_blinding,
+            }
+        }
+        fn inputs(&self) -> Vec<(usize, &SecretKey, &Scalar)> {
+            self.secrets
+                .iter()
+                .map(|(index, secret, blinding)| (*index, secret, blinding))
+                .collect()
+        }
+        fn sign(&self, data: impl AsRef<[u8]>) -> Result<Arcturus, Error> {
+            Arcturus::sign::<Sha512>(
+                &mut OsRng,
+                &self.inputs(),
+                self.rings.clone(),
+                &self.outputs,
+                &self.output_blinding,
+                data,
+            )
+        }
+    }
+    lazy_static! {
+        static ref SPEND: Spend = Spend::random(X, 2);
+    }
+    #[test]
+    fn sign_verify() {
+        for (len, inputs) in [(1, 1), (2, 2), (3, 1), (5, 3), (8, 4), (9, 2)] {
+            let spend = Spend::random(len, inputs);
+            let a = spend.sign(DATA_0).unwrap();
+            assert_eq!(a.images.len(), inputs);
+            assert_eq!(a.v.len(), one_of_many::digits(len));
+            assert!(a.verify::<Sha512>(DATA_0).is_ok());
+            assert_eq!(a.verify::<Sha512>(DATA_1), Err(Error::InvalidProof));
+        }
+    }
+    #[test]
+    fn sign_errors() {
+        let rng = &mut OsRng;
+        let inputs = SPEND.inputs();
+        let sign = |inputs: &[(usize, &SecretKey, &Scalar)],
+                    rings: Vec<Vec<PublicKey>>,
+                    output_blinding: &Scalar| {
+            Arcturus::sign::<Sha512>(
+                &mut OsRng,
+                inputs,
+                rings,
+                &SPEND.outputs,
+                output_blinding,
+                DATA_0,
+            )
+        };
+        assert_eq!(
+            sign(&[], SPEND.rings.clone(), &SPEND.output_blinding),
+            Err(Error::EmptySecrets)
+        );
+        assert_eq!(
+            sign(&inputs, SPEND.rings.clone(), &scalar_random(rng)),
+            Err(Error::Unbalanced)
+        );
+        assert_eq!(
+            sign(
+                &[inputs[0], inputs[0]],
+                SPEND.rings.clone(),
+                &SPEND.output_blinding
+            ),
+            Err(Error::DuplicateImage)
+        );
+        assert_eq!(
+            sign(
+                &[(X, inputs[0].1, inputs[0].2)],
+                SPEND.rings.clone(),
+                &SPEND.output_blinding
+            ),
+            Err(Error::SignerIndexOutOfRange)
+        );
+        assert_eq!(
+            sign(
+                &[(inputs[0].0, inputs[1].1, inputs[0].2)],
+                SPEND.rings.clone(),
+                &SPEND.output_blinding
+            ),
+            Err(Error::SignerKeyMismatch)
+        );
+        let rings = SPEND.rings.iter().map(|ring| ring[..1].to_vec()).collect();
+        assert_eq!(
+            sign(&inputs, rings, &SPEND.output_blinding),
+            Err(Error::RingWidthMismatch)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let a = SPEND.sign(DATA_0).unwrap();
+        let mut b = a.clone();
+        b.images.swap(0, 1);
+        assert_eq!(b.verify::<Sha512>(DATA_0), Err(Error::InvalidProof));
+        let mut c = a.clone();
+        c.f[0][0] = (Scalar::ONE + scalar_from_canonical(c.f[0][0]).unwrap()).to_bytes();
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::InvalidProof));
+        let mut d = a.clone();
+        d.outputs.pop();
+        assert_eq!(d.verify::<Sha512>(DATA_0), Err(Error::InvalidProof));
+        let mut e = a.clone();
+        e.v.pop();
+        assert_eq!(
+            e.verify::<Sha512>(DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut f = a.clone();
+        f.images[1] = f.images[0];
+        assert_eq!(f.verify::<Sha512>(DATA_0), Err(Error::DuplicateImage));
+        let mut g = a.clone();
+        g.images[1] = KeyImage::from(RistrettoPoint::default());
+        assert_eq!(
+            g.verify::<Sha512>(DATA_0),
+            Err(Error::InvalidKeyImage { index: 1 })
+        );
+        let mut h = a.clone();
+        h.outputs[0] = [0xff; 32];
+        assert_eq!(h.verify::<Sha512>(DATA_0), Err(Error::InvalidProofPoint));
+        let mut i = a.clone();
+        i.z_1[1] = [0xff; 32];
+        assert_eq!(
+            i.verify::<Sha512>(DATA_0),
+            Err(Error::NonCanonicalScalar { index: 7 })
+        );
+        let mut j = a;
+        j.images.clear();
+        assert_eq!(j.verify::<Sha512>(DATA_0), Err(Error::ImageCountMismatch));
+    }
+    #[test]
+    fn link() {
+        let rng = &mut OsRng;
+        let a = SPEND.sign(DATA_0).unwrap();
+        for (u, (_, secret, _)) in SPEND.secrets.iter().enumerate() {
+            let rings = (0..X)
+                .map(|_| {
+                    vec![
+                        PublicKey::from(point_random(rng)),
+                        PublicKey::from(point_random(rng)),
+                    ]
+                })
+                .collect();
+            let commitment = SecretKey::random(rng);
+            let b =
+                CLSAG::sign::<Sha512>(rng, &[secret.clone(), commitment], rings, DATA_1).unwrap();
+            assert_eq!(a.images[u], b.images[0]);
+            assert_eq!(a.images[u], secret.image::<Sha512>());
+        }
+    }
+    #[test]
+    fn serde() {
+        let a = SPEND.sign(DATA_0).unwrap();
+        let b: Arcturus = bincode::deserialize(&bincode::serialize(&a).unwrap()).unwrap();
+        assert_eq!(a, b);
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+    }
+    #[test]
+    fn context() {
+        let a = Arcturus::sign_with_context::<Sha512>(
+            &mut OsRng,
+            &SPEND.inputs(),
+            SPEND.rings.clone(),
+            &SPEND.outputs,
+            &SPEND.output_blinding,
+            b"zudo",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(a.verify_with_context::<Sha512>(b"zudo", DATA_0).is_ok());
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::InvalidProof));
+    }
+}
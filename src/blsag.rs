@@ -1,70 +1,216 @@
-use crate::image;
-use crate::point_from_slice;
-use crate::point_hash;
-use crate::scalar_from_canonical;
-use crate::scalar_from_hash;
-use crate::scalar_random;
-use crate::scalar_zero;
-use crate::RISTRETTO_BASEPOINT_POINT;
-use curve25519_dalek::ristretto::RistrettoPoint;
-use curve25519_dalek::traits::MultiscalarMul;
-use curve25519_dalek::Scalar;
-use digest::generic_array::typenum::U64;
-use digest::Digest;
+use crate::keys::KeyImage;
+use crate::keys::Ring;
+use crate::keys::SecretKey;
+use crate::legacy_version;
+use crate::members;
+use crate::members::Members;
+use crate::supported_version;
+use crate::Error;
+use crate::Group;
+use crate::HashToPointCache;
+use crate::RingHash;
+use crate::Ristretto255;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
 use rand::Rng;
 use rand_core::CryptoRngCore;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct BLSAG {
+#[serde(bound = "")]
+pub struct BLSAG<G: Group = Ristretto255> {
     pub challenge: [u8; 32],
     pub response: Vec<[u8; 32]>,
-    pub ring: Vec<[u8; 32]>,
-    pub image: [u8; 32],
+    pub ring: Ring<G>,
+    pub image: KeyImage<G>,
+    pub version: Version,
 }
 impl BLSAG {
-    pub fn sign<Hash: Digest<OutputSize = U64> + Clone>(
+    pub fn legacy(
+        challenge: [u8; 32],
+        response: Vec<[u8; 32]>,
+        ring: Ring,
+        image: KeyImage,
+    ) -> BLSAG {
+        BLSAG {
+            challenge,
+            response,
+            ring,
+            image,
+            version: Version::Legacy,
+        }
+    }
+}
+impl<G: Group> BLSAG<G> {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        data: impl AsRef<[u8]>,
+    ) -> Result<BLSAG<G>, Error> {
+        BLSAG::sign_with_context::<Hash>(rng, secret, ring, b"", data)
+    }
+    pub fn sign_with_context<Hash: RingHash>(
         rng: &mut impl CryptoRngCore,
-        secret: &Scalar,
-        mut ring: Vec<RistrettoPoint>,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        context: &[u8],
         data: impl AsRef<[u8]>,
-    ) -> Option<BLSAG> {
-        let image = image::<Hash>(secret);
+    ) -> Result<BLSAG<G>, Error> {
+        BLSAG::sign_with_version::<Hash>(rng, secret, ring, Version::V2, context, data)
+    }
+    pub fn sign_with_version<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        mut ring: Ring<G>,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<BLSAG<G>, Error> {
         let secret_index = rng.gen_range(0..=ring.len());
-        ring.insert(secret_index, secret * RISTRETTO_BASEPOINT_POINT);
+        ring.0.insert(secret_index, secret.public_key());
+        BLSAG::sign_with_index::<Hash>(rng, secret, ring, secret_index, version, context, data)
+    }
+    pub fn sign_canonical<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        mut ring: Ring<G>,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<BLSAG<G>, Error> {
+        let public_key = secret.public_key();
+        ring.0.push(public_key);
+        ring.canonicalize();
+        ring.validate()?;
+        let secret_index = ring.iter().position(|key| *key == public_key).unwrap();
+        BLSAG::sign_with_index::<Hash>(rng, secret, ring, secret_index, Version::V2, context, data)
+    }
+    pub fn sign_with_index<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<BLSAG<G>, Error> {
+        let members = Members::new(ring.chunks(1))?.hash::<Hash>(version, 1);
+        BLSAG::sign_members::<Hash>(
+            rng,
+            secret,
+            ring,
+            &members,
+            secret_index,
+            version,
+            context,
+            data,
+        )
+    }
+    pub fn sign_with_tag<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        tag: &[u8],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<BLSAG<G>, Error> {
+        BLSAG::sign_with_tag_and_version::<Hash>(rng, secret, ring, tag, Version::V2, context, data)
+    }
+    pub fn sign_with_tag_and_version<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        mut ring: Ring<G>,
+        tag: &[u8],
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<BLSAG<G>, Error> {
+        let secret_index = rng.gen_range(0..=ring.len());
+        ring.0.insert(secret_index, secret.public_key());
+        let members = Members::new(ring.chunks(1))?.hash_tagged::<Hash>(version, tag, 1);
+        BLSAG::sign_members::<Hash>(
+            rng,
+            secret,
+            ring,
+            &members,
+            secret_index,
+            version,
+            context,
+            data,
+        )
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign_with_index_and_cache<Hash: RingHash + 'static>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<BLSAG<G>, Error> {
+        let members = Members::new(ring.chunks(1))?.hash_cached::<Hash>(version, 1, cache);
+        BLSAG::sign_members::<Hash>(
+            rng,
+            secret,
+            ring,
+            &members,
+            secret_index,
+            version,
+            context,
+            data,
+        )
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn sign_members<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        members: &Members<G>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<BLSAG<G>, Error> {
+        supported_version(version)?;
+        if secret_index >= ring.len() {
+            return Err(Error::SignerIndexOutOfRange);
+        }
+        if ring[secret_index] != secret.public_key() {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let image = KeyImage(members.hashes(secret_index)[0] * secret.0);
         let x = ring.len();
-        let hash = Hash::new().chain_update(data);
-        let mut hashes = (0..x).map(|_| hash.clone()).collect::<Vec<_>>();
+        let transcript = BLSAG::transcript::<Hash>(version, context, members, &image, data)?;
+        let mut transcripts = (0..x).map(|_| transcript.clone()).collect::<Vec<_>>();
         let mut current_index = (secret_index + 1) % x;
-        let r = scalar_random(rng);
-        hashes[current_index].update((r * RISTRETTO_BASEPOINT_POINT).compress().as_bytes());
-        hashes[current_index].update(
-            (r * point_hash::<Hash>(ring[secret_index]))
-                .compress()
-                .as_bytes(),
-        );
-        let mut challenges = vec![scalar_zero(); x];
-        challenges[current_index] = scalar_from_hash(hashes[current_index].clone());
-        let mut response = (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+        let r = Zeroizing::new(G::scalar_random(rng));
+        transcripts[current_index].append_element::<G>(b"L", &(G::generator() * *r));
+        transcripts[current_index]
+            .append_element::<G>(b"R", &(members.hashes(secret_index)[0] * *r));
+        let mut challenges = vec![G::scalar_from_u64(0); x];
+        challenges[current_index] = transcripts[current_index].challenge::<G>(b"c");
+        let mut response = (0..x).map(|_| G::scalar_random(rng)).collect::<Vec<_>>();
         loop {
             let next_index = (current_index + 1) % x;
-            hashes[next_index].update(
-                RistrettoPoint::multiscalar_mul(
+            transcripts[next_index].append_element::<G>(
+                b"L",
+                &G::multiscalar_mul(
                     &[response[current_index], challenges[current_index]],
-                    &[RISTRETTO_BASEPOINT_POINT, ring[current_index]],
-                )
-                .compress()
-                .as_bytes(),
+                    &[G::generator(), ring[current_index].0],
+                ),
             );
-            hashes[next_index].update(
-                RistrettoPoint::multiscalar_mul(
+            transcripts[next_index].append_element::<G>(
+                b"R",
+                &G::multiscalar_mul(
                     &[response[current_index], challenges[current_index]],
-                    &[point_hash::<Hash>(ring[current_index]), image],
-                )
-                .compress()
-                .as_bytes(),
+                    &[members.hashes(current_index)[0], image.0],
+                ),
             );
-            challenges[next_index] = scalar_from_hash(hashes[next_index].clone());
+            challenges[next_index] = transcripts[next_index].challenge::<G>(b"c");
             if (secret_index >= 1 && current_index == (secret_index - 1) % x)
                 || (secret_index == 0 && current_index == x - 1)
             {
@@ -72,81 +218,319 @@ impl BLSAG {
             }
             current_index = next_index;
         }
-        response[secret_index] = r - (challenges[secret_index] * secret);
-        Some(BLSAG {
-            challenge: challenges[0].to_bytes(),
-            response: response.iter().map(|scalar| scalar.to_bytes()).collect(),
-            ring: ring
-                .iter()
-                .map(|point| point.compress().to_bytes())
-                .collect(),
-            image: image.compress().to_bytes(),
+        response[secret_index] = *r - *Zeroizing::new(challenges[secret_index] * secret.0);
+        Ok(BLSAG {
+            challenge: G::scalar_to_bytes(&challenges[0]),
+            response: response.iter().map(G::scalar_to_bytes).collect(),
+            ring,
+            image,
+            version,
         })
     }
-    pub fn verify<Hash: Digest<OutputSize = U64> + Clone>(&self, data: impl AsRef<[u8]>) -> bool {
-        match || -> Option<bool> {
-            let hash = Hash::new().chain_update(data);
-            let challenge_0 = scalar_from_canonical(self.challenge)?;
-            let mut challenge_1 = challenge_0;
-            let response = self
-                .response
-                .iter()
-                .map(|&bytes| scalar_from_canonical(bytes))
-                .collect::<Option<Vec<_>>>()?;
-            let ring = self
-                .ring
-                .iter()
-                .map(|bytes| point_from_slice(bytes))
-                .collect::<Option<Vec<_>>>()?;
-            let image = point_from_slice(&self.image)?;
-            for i in 0..ring.len() {
-                let mut hash = hash.clone();
-                hash.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[response[i], challenge_1],
-                        &[RISTRETTO_BASEPOINT_POINT, ring[i]],
-                    )
-                    .compress()
-                    .as_bytes(),
-                );
-                hash.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[response[i], challenge_1],
-                        &[point_hash::<Hash>(ring[i]), image],
-                    )
-                    .compress()
-                    .as_bytes(),
-                );
-                challenge_1 = scalar_from_hash(hash);
-            }
-            Some(challenge_0 == challenge_1)
-        }() {
-            Some(x) => x,
-            None => return false,
+    pub fn verify<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(b"", data)
+    }
+    pub fn verify_legacy<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        legacy_version(self.version)?;
+        BLSAG::verify_transcript::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.ring.chunks(1))?.hash::<Hash>(self.version, 1),
+            &self.image,
+            self.version,
+            b"",
+            data,
+        )
+    }
+    pub fn verify_canonical<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if !self.ring.is_canonical() {
+            return Err(Error::NonCanonicalRing);
         }
+        self.ring.validate()?;
+        self.verify_with_context::<Hash>(context, data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        BLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.ring.chunks(1))?.hash::<Hash>(self.version, 1),
+            &self.image,
+            self.version,
+            context,
+            data,
+        )
     }
-    pub fn link(images: &[[u8; 32]]) -> bool {
+    pub fn verify_with_tag<Hash: RingHash>(
+        &self,
+        tag: &[u8],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        BLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.ring.chunks(1))?.hash_tagged::<Hash>(self.version, tag, 1),
+            &self.image,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_with_cache<Hash: RingHash + 'static>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Error> {
+        BLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.ring.chunks(1))?.hash_cached::<Hash>(self.version, 1, cache),
+            &self.image,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_batch<Hash: RingHash + 'static>(
+        batch: &[(&BLSAG<G>, &[u8])],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        BLSAG::verify_batch_with_context::<Hash>(batch, b"")
+    }
+    pub fn verify_batch_with_context<Hash: RingHash + 'static>(
+        batch: &[(&BLSAG<G>, &[u8])],
+        context: &[u8],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        BLSAG::verify_batch_with_cache::<Hash>(batch, context, &mut HashMap::new())
+    }
+    pub fn verify_batch_with_cache<Hash: RingHash + 'static>(
+        batch: &[(&BLSAG<G>, &[u8])],
+        context: &[u8],
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Vec<(usize, Error)>> {
+        members::verify_batch::<_, Hash, _>(
+            batch,
+            cache,
+            |blsag| Ok((Members::new(blsag.ring.chunks(1))?, blsag.version, 1)),
+            |blsag, members, data| {
+                BLSAG::verify_parts::<Hash>(
+                    blsag.challenge,
+                    &blsag.response,
+                    members,
+                    &blsag.image,
+                    blsag.version,
+                    context,
+                    data,
+                )
+            },
+        )
+    }
+    pub fn link(images: &[KeyImage<G>]) -> bool {
         if images.is_empty() {
             return false;
         }
-        images.iter().skip(1).all(|bytes| bytes == &images[0])
+        images.iter().skip(1).all(|image| image == &images[0])
+    }
+    pub fn link_with_tags<Hash: RingHash>(
+        signatures: &[(&BLSAG<G>, &[u8], &[u8])],
+        context: &[u8],
+    ) -> Result<bool, Error> {
+        for (signature, tag, data) in signatures {
+            signature.verify_with_tag::<Hash>(tag, context, data)?;
+        }
+        if signatures.is_empty() {
+            return Ok(false);
+        }
+        let (first, first_tag, _) = signatures[0];
+        if signatures.iter().any(|(_, tag, _)| *tag != first_tag) {
+            return Err(Error::TagMismatch);
+        }
+        Ok(signatures
+            .iter()
+            .skip(1)
+            .all(|(signature, _, _)| signature.image == first.image))
+    }
+    pub fn detach(self) -> (DetachedBLSAG<G>, Ring<G>) {
+        (
+            DetachedBLSAG {
+                challenge: self.challenge,
+                response: self.response,
+                image: self.image,
+                version: self.version,
+            },
+            self.ring,
+        )
+    }
+    fn verify_parts<Hash: RingHash>(
+        challenge: [u8; 32],
+        response: &[[u8; 32]],
+        ring: &Members<G>,
+        image: &KeyImage<G>,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        supported_version(version)?;
+        BLSAG::verify_transcript::<Hash>(challenge, response, ring, image, version, context, data)
+    }
+    fn verify_transcript<Hash: RingHash>(
+        challenge: [u8; 32],
+        response: &[[u8; 32]],
+        ring: &Members<G>,
+        image: &KeyImage<G>,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if response.len() != ring.len() {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let transcript = BLSAG::transcript::<Hash>(version, context, ring, image, data)?;
+        let challenge_0 =
+            G::scalar_from_canonical(challenge).ok_or(Error::NonCanonicalChallenge)?;
+        let mut challenge_1 = challenge_0;
+        let response = response
+            .iter()
+            .enumerate()
+            .map(|(index, &bytes)| {
+                G::scalar_from_canonical(bytes).ok_or(Error::NonCanonicalScalar { index })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, response) in response.iter().enumerate() {
+            let mut transcript = transcript.clone();
+            transcript.append_element::<G>(
+                b"L",
+                &G::vartime_multiscalar_mul(
+                    &[*response, challenge_1],
+                    &[G::generator(), ring.points[i]],
+                ),
+            );
+            transcript.append_element::<G>(
+                b"R",
+                &G::vartime_multiscalar_mul(
+                    &[*response, challenge_1],
+                    &[ring.hashes(i)[0], image.0],
+                ),
+            );
+            challenge_1 = transcript.challenge::<G>(b"c");
+        }
+        if challenge_0 != challenge_1 {
+            return Err(Error::ChallengeMismatch);
+        }
+        Ok(())
+    }
+    fn transcript<Hash: RingHash>(
+        version: Version,
+        context: &[u8],
+        ring: &Members<G>,
+        image: &KeyImage<G>,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript = Transcript::versioned(version, b"ring-signature/bLSAG/v2", context)?;
+        if let Some(tag) = &ring.tag {
+            transcript.append_message(b"tag", tag);
+        }
+        if version != Version::Legacy {
+            for key in &ring.bytes {
+                transcript.append_encoding::<G>(b"ring", key);
+            }
+            transcript.append_element::<G>(b"image", &image.0);
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+}
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct DetachedBLSAG<G: Group = Ristretto255> {
+    pub challenge: [u8; 32],
+    pub response: Vec<[u8; 32]>,
+    pub image: KeyImage<G>,
+    pub version: Version,
+}
+impl<G: Group> DetachedBLSAG<G> {
+    pub fn attach(self, ring: Ring<G>) -> BLSAG<G> {
+        BLSAG {
+            challenge: self.challenge,
+            response: self.response,
+            ring,
+            image: self.image,
+            version: self.version,
+        }
+    }
+    pub fn verify_with_ring<Hash: RingHash>(
+        &self,
+        ring: &Ring<G>,
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        self.verify_with_ring_and_context::<Hash>(ring, b"", data)
+    }
+    pub fn verify_with_ring_and_context<Hash: RingHash>(
+        &self,
+        ring: &Ring<G>,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        BLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(ring.chunks(1))?.hash::<Hash>(self.version, 1),
+            &self.image,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_with_ring_and_cache<Hash: RingHash + 'static>(
+        &self,
+        ring: &Ring<G>,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Error> {
+        BLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(ring.chunks(1))?.hash_cached::<Hash>(self.version, 1, cache),
+            &self.image,
+            self.version,
+            context,
+            data,
+        )
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::point_random;
+    use crate::LruCache;
+    use crate::RistrettoPoint;
+    use crate::Wide;
+    use crate::Xof;
     use lazy_static::lazy_static;
     use rand_core::OsRng;
     use sha2::Sha512;
+    use sha3::Keccak256;
+    use sha3::Sha3_512;
+    use sha3::Shake256;
     const DATA_0: &[u8] = b"hello from";
     const DATA_1: &str = "zudo";
     const X: usize = 2;
+    const LEGACY: &str = "3ee6cde35f34a216e3298c627856e55404b71c57d3cd420e8b24e1a76cd1470902000000000000001b003f12da64c4ab27a964c7c59c124c2abe149158a81106a3cd4722067b8a09c0c4735678970d45bb21fa08845c26b468c72f5312ac77aa8e8958dddba89b050200000000000000fad889239a9916519e4e2cebf7d01df8b4531582431f1585b2d7f3f2ebb1c379d813b6bd665f873584618ef61ad6eb210588bd98abb0b2fe3467cff4bc66756cb8f8e2bad830cbee94d5b02861048cc1e4cbce7e03b6cba77070bbce77fc452b";
+    const V1: &str = "3f65e4d00fd5128424550cbcb1829f526d7330210c527e6dd5e967a57c07ba0602000000000000007824eb6046933b396179d57475a7af6cd813cc2db8bf1dd9d5f0ed36f94dba0b16a74df105140c109d113071019351789cac1b33ca782d29484ee9d70b5a960a020000000000000020ed0e80d00d08d5bbb3500ea679503e0648bbb16bf75b0a8427f6f793f05f4160eb5a212b1dce855b4d997fb772a787683224049a003e54ff71991b5b5e762ab06d684b3cd88aae7e0451723b3efb10e894f5d12fd319ffdd9fff9cfa116a6101000000";
     lazy_static! {
-        static ref SECRET_0: Scalar = scalar_random(&mut OsRng);
-        static ref SECRET_1: Scalar = scalar_random(&mut OsRng);
-        static ref RING_0: Vec<RistrettoPoint> = (0..X).map(|_| point_random(&mut OsRng)).collect();
-        static ref RING_1: Vec<RistrettoPoint> = (0..X).map(|_| point_random(&mut OsRng)).collect();
+        static ref SECRET_0: SecretKey = SecretKey::random(&mut OsRng);
+        static ref SECRET_1: SecretKey = SecretKey::random(&mut OsRng);
+        static ref RING_0: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
+        static ref RING_1: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
     }
     #[test]
     fn sign_verify() {
@@ -155,10 +539,207 @@ mod tests {
         let b = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_1.clone(), DATA_0).unwrap();
         let c = BLSAG::sign::<Sha512>(rng, &SECRET_1, RING_0.clone(), DATA_0).unwrap();
         let d = BLSAG::sign::<Sha512>(rng, &SECRET_1, RING_1.clone(), DATA_0).unwrap();
-        assert!((a.verify::<Sha512>(DATA_0)));
-        assert!((b.verify::<Sha512>(DATA_0)));
-        assert!((c.verify::<Sha512>(DATA_0)));
-        assert!((d.verify::<Sha512>(DATA_0)));
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+        assert!(c.verify::<Sha512>(DATA_0).is_ok());
+        assert!(d.verify::<Sha512>(DATA_0).is_ok());
+    }
+    #[test]
+    fn sign_with_index() {
+        let rng = &mut OsRng;
+        let mut ring = RING_0.clone();
+        ring.0.insert(1, SECRET_0.public_key());
+        let a = BLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRET_0,
+            ring.clone(),
+            1,
+            Version::V2,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert_eq!(a.ring, ring);
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            BLSAG::sign_with_index::<Sha512>(
+                rng,
+                &SECRET_0,
+                ring.clone(),
+                0,
+                Version::V2,
+                b"",
+                DATA_0
+            ),
+            Err(Error::SignerKeyMismatch)
+        );
+        assert_eq!(
+            BLSAG::sign_with_index::<Sha512>(rng, &SECRET_0, ring, X + 1, Version::V2, b"", DATA_0),
+            Err(Error::SignerIndexOutOfRange)
+        );
+    }
+    #[test]
+    fn canonical() {
+        let rng = &mut OsRng;
+        let a =
+            BLSAG::sign_canonical::<Sha512>(rng, &SECRET_0, RING_0.clone(), b"", DATA_0).unwrap();
+        assert!(a.ring.is_canonical());
+        assert!(a.verify_canonical::<Sha512>(b"", DATA_0).is_ok());
+        let mut ring = a.ring.clone();
+        ring.0.reverse();
+        let index = ring
+            .iter()
+            .position(|key| *key == SECRET_0.public_key())
+            .unwrap();
+        let b =
+            BLSAG::sign_with_index::<Sha512>(rng, &SECRET_0, ring, index, Version::V2, b"", DATA_0)
+                .unwrap();
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            b.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::NonCanonicalRing)
+        );
+        let mut c = a.clone();
+        c.ring.0[0] = c.ring[1];
+        assert_eq!(
+            c.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::NonCanonicalRing)
+        );
+        let mut ring = RING_0.clone();
+        ring.0.push(RING_0[0]);
+        ring.0.push(SECRET_0.public_key());
+        assert!(BLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRET_0,
+            ring,
+            X + 1,
+            Version::V2,
+            b"",
+            DATA_0
+        )
+        .is_ok());
+        let mut ring = RING_0.clone();
+        ring.0.push(RING_0[0]);
+        assert!(matches!(
+            BLSAG::sign_canonical::<Sha512>(rng, &SECRET_0, ring, b"", DATA_0),
+            Err(Error::DuplicateMember { .. })
+        ));
+        let mut ring = RING_0.clone();
+        ring.0.push(RistrettoPoint::default().into());
+        assert_eq!(
+            BLSAG::sign_canonical::<Sha512>(rng, &SECRET_0, ring.clone(), b"", DATA_0),
+            Err(Error::IdentityMember { row: 0, col: 0 })
+        );
+        ring.0.push(SECRET_0.public_key());
+        ring.canonicalize();
+        let index = ring
+            .iter()
+            .position(|key| *key == SECRET_0.public_key())
+            .unwrap();
+        let d =
+            BLSAG::sign_with_index::<Sha512>(rng, &SECRET_0, ring, index, Version::V2, b"", DATA_0)
+                .unwrap();
+        assert!(d.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            d.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::IdentityMember { row: 0, col: 0 })
+        );
+    }
+    #[test]
+    fn detach() {
+        let rng = &mut OsRng;
+        let a = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        let (b, ring) = a.clone().detach();
+        assert!(b.verify_with_ring::<Sha512>(&ring, DATA_0).is_ok());
+        assert!(bincode::serialize(&b).unwrap().len() < bincode::serialize(&a).unwrap().len());
+        let mut reversed = ring.clone();
+        reversed.0.reverse();
+        assert_eq!(
+            b.verify_with_ring::<Sha512>(&reversed, DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(b.attach(ring), a);
+    }
+    #[test]
+    fn verify_batch() {
+        let rng = &mut OsRng;
+        let a = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        let b = BLSAG::sign::<Sha512>(rng, &SECRET_1, RING_0.clone(), DATA_0).unwrap();
+        let c = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_1.clone(), DATA_0).unwrap();
+        assert!(BLSAG::verify_batch::<Sha512>(&[(&a, DATA_0), (&b, DATA_0), (&c, DATA_0)]).is_ok());
+        let mut d = b.clone();
+        d.challenge = [0xff; 32];
+        assert_eq!(
+            BLSAG::verify_batch::<Sha512>(&[(&a, DATA_0), (&d, DATA_0), (&c, DATA_1.as_bytes())]),
+            Err(vec![
+                (1, Error::NonCanonicalChallenge),
+                (2, Error::ChallengeMismatch)
+            ])
+        );
+    }
+    #[test]
+    fn cache() {
+        let rng = &mut OsRng;
+        let mut cache = LruCache::new(16);
+        let mut ring = RING_0.clone();
+        ring.0.push(SECRET_0.public_key());
+        let a = BLSAG::sign_with_index_and_cache::<Sha512>(
+            rng,
+            &SECRET_0,
+            ring.clone(),
+            X,
+            Version::V2,
+            b"",
+            DATA_0,
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(cache.len(), X + 1);
+        assert!(a
+            .verify_with_cache::<Sha512>(b"", DATA_0, &mut cache)
+            .is_ok());
+        let b = BLSAG::sign_with_index_and_cache::<Sha3_512>(
+            rng,
+            &SECRET_0,
+            ring,
+            X,
+            Version::V2,
+            b"",
+            DATA_0,
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(cache.len(), 2 * (X + 1));
+        assert!(b.verify::<Sha3_512>(DATA_0).is_ok());
+        assert!(a
+            .verify_with_cache::<Sha512>(b"", DATA_0, &mut cache)
+            .is_ok());
+        assert_eq!(
+            b.verify_with_cache::<Sha512>(b"", DATA_0, &mut cache),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let rng = &mut OsRng;
+        let a = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        assert_eq!(a.verify::<Sha512>(DATA_1), Err(Error::ChallengeMismatch));
+        let mut b = a.clone();
+        b.ring.0.pop();
+        assert_eq!(
+            b.verify::<Sha512>(DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut c = a.clone();
+        c.challenge = [0xff; 32];
+        assert_eq!(
+            c.verify::<Sha512>(DATA_0),
+            Err(Error::NonCanonicalChallenge)
+        );
+        let mut bytes = bincode::serialize(&a).unwrap();
+        let len = bytes.len();
+        bytes[len - 32..].copy_from_slice(&[0xff; 32]);
+        assert!(bincode::deserialize::<BLSAG>(&bytes).is_err());
     }
     #[test]
     fn link() {
@@ -175,4 +756,243 @@ mod tests {
         assert!((!BLSAG::link(&[a.image, e.image])));
         assert!((!BLSAG::link(&[a.image, f.image])));
     }
+    #[test]
+    #[allow(deprecated)]
+    fn legacy() {
+        let (challenge, response, ring, image) =
+            bincode::deserialize(&hex::decode(LEGACY).unwrap()).unwrap();
+        let a = BLSAG::legacy(challenge, response, ring, image);
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        assert!(a.verify_legacy::<Sha512>(DATA_0).is_ok());
+        let mut b = a.clone();
+        b.version = Version::V1;
+        assert_eq!(
+            b.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        let rng = &mut OsRng;
+        let mut c = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        c.version = Version::Legacy;
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        assert_eq!(
+            BLSAG::verify_batch::<Sha512>(&[(&c, DATA_0)]),
+            Err(vec![(0, Error::UnsupportedVersion)])
+        );
+        let (detached, ring) = c.clone().detach();
+        assert_eq!(
+            detached.verify_with_ring::<Sha512>(&ring, DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(
+            c.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        for version in [Version::Legacy, Version::V1] {
+            assert_eq!(
+                BLSAG::sign_with_version::<Sha512>(
+                    rng,
+                    &SECRET_0,
+                    RING_0.clone(),
+                    version,
+                    b"",
+                    DATA_0
+                ),
+                Err(Error::UnsupportedVersion)
+            );
+        }
+    }
+    #[test]
+    #[allow(deprecated)]
+    fn context() {
+        let a: BLSAG = bincode::deserialize(&hex::decode(V1).unwrap()).unwrap();
+        assert_eq!(a.version, Version::V1);
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        assert!(a.verify_legacy::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            a.verify_with_context::<Sha512>(b"zudo", DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        let rng = &mut OsRng;
+        let b = BLSAG::sign_with_context::<Sha512>(rng, &SECRET_0, RING_0.clone(), b"zudo", DATA_0)
+            .unwrap();
+        assert_eq!(b.version, Version::V2);
+        assert!(b.verify_with_context::<Sha512>(b"zudo", DATA_0).is_ok());
+        assert_eq!(
+            b.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(b.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+        assert_eq!(
+            b.verify_with_context::<Sha512>(b"odu", DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn version() {
+        let rng = &mut OsRng;
+        let a = BLSAG::sign_with_version::<Sha512>(
+            rng,
+            &SECRET_0,
+            RING_0.clone(),
+            Version::V3,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        let b = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        assert_eq!(a.version, Version::V3);
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(a.image, SECRET_0.image_with_version::<Sha512>(Version::V3));
+        assert_ne!(a.image, b.image);
+        let mut cache = LruCache::new(2 * X + 2);
+        assert!(BLSAG::verify_batch_with_cache::<Sha512>(
+            &[(&a, DATA_0), (&b, DATA_0)],
+            b"",
+            &mut cache
+        )
+        .is_ok());
+        assert_eq!(cache.len(), 2 * X + 2);
+        let mut c = a.clone();
+        c.version = Version::V2;
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+    }
+    #[test]
+    fn ring_hash() {
+        let rng = &mut OsRng;
+        let a = BLSAG::sign::<Xof<Shake256>>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        let b = BLSAG::sign::<Wide<Keccak256>>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        assert!(a.verify::<Xof<Shake256>>(DATA_0).is_ok());
+        assert!(b.verify::<Wide<Keccak256>>(DATA_0).is_ok());
+        assert_eq!(
+            a.verify::<Wide<Keccak256>>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+    }
+    #[test]
+    fn tag() {
+        let rng = &mut OsRng;
+        let a =
+            BLSAG::sign_with_tag::<Sha512>(rng, &SECRET_0, RING_0.clone(), b"2024", b"", DATA_0)
+                .unwrap();
+        let b =
+            BLSAG::sign_with_tag::<Sha512>(rng, &SECRET_0, RING_1.clone(), b"2024", b"", DATA_1)
+                .unwrap();
+        let c =
+            BLSAG::sign_with_tag::<Sha512>(rng, &SECRET_0, RING_0.clone(), b"2028", b"", DATA_0)
+                .unwrap();
+        let d = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        assert!(a.verify_with_tag::<Sha512>(b"2024", b"", DATA_0).is_ok());
+        assert!(b.verify_with_tag::<Sha512>(b"2024", b"", DATA_1).is_ok());
+        assert!(c.verify_with_tag::<Sha512>(b"2028", b"", DATA_0).is_ok());
+        assert_eq!(
+            a.verify_with_tag::<Sha512>(b"2028", b"", DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+        assert_eq!(
+            d.verify_with_tag::<Sha512>(b"", b"", DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(a.image, SECRET_0.image_with_tag::<Sha512>(b"2024"));
+        assert_ne!(a.image, c.image);
+        assert_ne!(a.image, d.image);
+        assert_eq!(
+            BLSAG::link_with_tags::<Sha512>(
+                &[(&a, b"2024", DATA_0), (&b, b"2024", DATA_1.as_bytes())],
+                b""
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            BLSAG::link_with_tags::<Sha512>(&[(&a, b"2024", DATA_0), (&c, b"2028", DATA_0)], b""),
+            Err(Error::TagMismatch)
+        );
+        assert_eq!(
+            BLSAG::link_with_tags::<Sha512>(&[(&a, b"2024", DATA_0), (&a, b"2028", DATA_0)], b""),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(
+            BLSAG::<Ristretto255>::link_with_tags::<Sha512>(&[], b""),
+            Ok(false)
+        );
+        let e = BLSAG::sign_with_tag_and_version::<Sha512>(
+            rng,
+            &SECRET_0,
+            RING_0.clone(),
+            b"2024",
+            Version::V3,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(e.verify_with_tag::<Sha512>(b"2024", b"", DATA_0).is_ok());
+        assert_eq!(
+            e.image,
+            SECRET_0.image_with_tag_and_version::<Sha512>(b"2024", Version::V3)
+        );
+        assert_eq!(
+            BLSAG::link_with_tags::<Sha512>(&[(&a, b"2024", DATA_0), (&e, b"2024", DATA_0)], b""),
+            Ok(false)
+        );
+    }
+    fn group<G: Group>() {
+        let rng = &mut OsRng;
+        let secret = SecretKey::<G>::random(rng);
+        let ring = (0..X)
+            .map(|_| SecretKey::<G>::random(rng).public_key())
+            .collect::<Ring<G>>();
+        let a = BLSAG::sign::<Sha512>(rng, &secret, ring.clone(), DATA_0).unwrap();
+        let b = BLSAG::sign::<Sha512>(rng, &secret, ring, DATA_1).unwrap();
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(a.verify::<Sha512>(DATA_1), Err(Error::ChallengeMismatch));
+        assert_eq!(a.image, secret.image::<Sha512>());
+        assert!(BLSAG::link(&[a.image, b.image]));
+        let bytes = bincode::serialize(&a).unwrap();
+        assert_eq!(bincode::deserialize::<BLSAG<G>>(&bytes).unwrap(), a);
+    }
+    #[test]
+    fn groups() {
+        group::<Ristretto255>();
+        group::<crate::Ed25519>();
+        #[cfg(feature = "secp256k1")]
+        group::<crate::Secp256k1>();
+        #[cfg(feature = "jubjub")]
+        group::<crate::Jubjub>();
+    }
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn secp256k1() {
+        let rng = &mut OsRng;
+        let secret = SecretKey::<crate::Secp256k1>::random(rng);
+        let keys = (0..X)
+            .map(|_| {
+                SecretKey::<crate::Secp256k1>::random(rng)
+                    .public_key()
+                    .to_sec1(false)
+            })
+            .collect::<Vec<_>>();
+        let ring = Ring::from_sec1(&keys).unwrap();
+        let a = BLSAG::sign_with_version::<Sha512>(rng, &secret, ring, Version::V3, b"", DATA_0)
+            .unwrap();
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(a.image, secret.image_with_version::<Sha512>(Version::V3));
+    }
+    #[cfg(feature = "jubjub")]
+    #[test]
+    fn jubjub() {
+        use crate::Poseidon;
+        let rng = &mut OsRng;
+        let secret = SecretKey::<crate::Jubjub>::random(rng);
+        let ring = (0..X)
+            .map(|_| SecretKey::<crate::Jubjub>::random(rng).public_key())
+            .collect::<Ring<_>>();
+        let a = BLSAG::sign::<Poseidon>(rng, &secret, ring.clone(), DATA_0).unwrap();
+        let b = BLSAG::sign::<Poseidon>(rng, &secret, ring, DATA_1).unwrap();
+        assert!(a.verify::<Poseidon>(DATA_0).is_ok());
+        assert!(b.verify::<Poseidon>(DATA_1).is_ok());
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+        assert_eq!(a.image, secret.image::<Poseidon>());
+        assert!(BLSAG::link(&[a.image, b.image]));
+    }
 }
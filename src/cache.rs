@@ -0,0 +1,148 @@
+use crate::Group;
+use crate::RingHash;
+use crate::Ristretto255;
+use crate::Version;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+pub trait HashToPointCache<G: Group = Ristretto255> {
+    fn get(&mut self, hash: TypeId, key: &G::Encoding) -> Option<G::Point>;
+    fn insert(&mut self, hash: TypeId, key: G::Encoding, point: G::Point);
+}
+impl<G: Group> HashToPointCache<G> for HashMap<(TypeId, G::Encoding), G::Point> {
+    fn get(&mut self, hash: TypeId, key: &G::Encoding) -> Option<G::Point> {
+        HashMap::get(self, &(hash, *key)).copied()
+    }
+    fn insert(&mut self, hash: TypeId, key: G::Encoding, point: G::Point) {
+        HashMap::insert(self, (hash, key), point);
+    }
+}
+pub struct LruCache<G: Group = Ristretto255> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<(TypeId, G::Encoding), (G::Point, u64)>,
+    order: BTreeMap<u64, (TypeId, G::Encoding)>,
+}
+impl<G: Group> LruCache<G> {
+    pub fn new(capacity: usize) -> LruCache<G> {
+        LruCache {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    fn touch(&mut self, key: (TypeId, G::Encoding)) -> Option<G::Point> {
+        let (point, tick) = self.entries.get_mut(&key)?;
+        self.order.remove(tick);
+        self.tick += 1;
+        *tick = self.tick;
+        self.order.insert(self.tick, key);
+        Some(*point)
+    }
+}
+impl<G: Group> HashToPointCache<G> for LruCache<G> {
+    fn get(&mut self, hash: TypeId, key: &G::Encoding) -> Option<G::Point> {
+        self.touch((hash, *key))
+    }
+    fn insert(&mut self, hash: TypeId, key: G::Encoding, point: G::Point) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some((entry, _)) = self.entries.get_mut(&(hash, key)) {
+            *entry = point;
+            self.touch((hash, key));
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert((hash, key), (point, self.tick));
+        self.order.insert(self.tick, (hash, key));
+    }
+}
+struct Xmd;
+fn cache_key<Hash: 'static>(version: Version) -> TypeId {
+    match version {
+        Version::V3 => TypeId::of::<Xmd>(),
+        _ => TypeId::of::<Hash>(),
+    }
+}
+pub(crate) fn point_hash_cached<G: Group, Hash: RingHash + 'static>(
+    version: Version,
+    cache: &mut impl HashToPointCache<G>,
+    key: &G::Encoding,
+) -> G::Point {
+    let hash = cache_key::<Hash>(version);
+    if let Some(point) = cache.get(hash, key) {
+        return point;
+    }
+    let point = G::hash_to_point::<Hash>(version, key.as_ref());
+    cache.insert(hash, *key, point);
+    point
+}
+pub(crate) fn fill<'a, G: Group, Hash: RingHash + 'static>(
+    cache: &mut impl HashToPointCache<G>,
+    keys: impl IntoIterator<Item = (Version, &'a G::Encoding)>,
+) {
+    let mut seen = HashSet::new();
+    let missing = keys
+        .into_iter()
+        .filter(|(version, key)| {
+            let hash = cache_key::<Hash>(*version);
+            seen.insert((hash, **key)) && cache.get(hash, key).is_none()
+        })
+        .map(|(version, key)| (version, *key))
+        .collect::<Vec<_>>();
+    let points = iter!(&missing)
+        .map(|(version, key)| G::hash_to_point::<Hash>(*version, key.as_ref()))
+        .collect::<Vec<_>>();
+    for ((version, key), point) in missing.into_iter().zip(points) {
+        cache.insert(cache_key::<Hash>(version), key, point);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_hash;
+    use crate::point_random;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    use sha3::Sha3_512;
+    #[test]
+    fn lru() {
+        let points = (0..3).map(|_| point_random(&mut OsRng)).collect::<Vec<_>>();
+        let keys = points
+            .iter()
+            .map(|point| point.compress().to_bytes())
+            .collect::<Vec<_>>();
+        let mut cache = LruCache::new(2);
+        assert_eq!(
+            point_hash_cached::<Ristretto255, Sha512>(Version::V2, &mut cache, &keys[0]),
+            point_hash::<Sha512>(points[0])
+        );
+        assert_eq!(
+            point_hash_cached::<Ristretto255, Sha3_512>(Version::V2, &mut cache, &keys[0]),
+            point_hash::<Sha3_512>(points[0])
+        );
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(TypeId::of::<Sha512>(), &keys[0]).is_some());
+        point_hash_cached::<Ristretto255, Sha512>(Version::V2, &mut cache, &keys[1]);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(TypeId::of::<Sha3_512>(), &keys[0]).is_none());
+        assert!(cache.get(TypeId::of::<Sha512>(), &keys[0]).is_some());
+        assert!(cache.get(TypeId::of::<Sha512>(), &keys[1]).is_some());
+    }
+}
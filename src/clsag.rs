@@ -1,100 +1,261 @@
-use crate::images;
-use crate::point_from_slice;
-use crate::point_hash;
-use crate::scalar_from_canonical;
-use crate::scalar_from_hash;
-use crate::scalar_random;
-use crate::scalar_zero;
-use crate::RISTRETTO_BASEPOINT_POINT;
-use curve25519_dalek::ristretto::RistrettoPoint;
-use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::traits::MultiscalarMul;
-use digest::generic_array::typenum::U64;
-use digest::Digest;
+use crate::keys;
+use crate::keys::KeyImage;
+use crate::keys::PublicKey;
+use crate::keys::SecretKey;
+use crate::legacy_version;
+use crate::members;
+use crate::members::Members;
+use crate::supported_version;
+use crate::Error;
+use crate::Group;
+use crate::HashToPointCache;
+use crate::RingHash;
+use crate::Ristretto255;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
 use rand::Rng;
 use rand_core::CryptoRngCore;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct CLSAG {
+#[serde(bound = "")]
+pub struct CLSAG<G: Group = Ristretto255> {
     pub challenge: [u8; 32],
     pub response: Vec<[u8; 32]>,
-    pub rings: Vec<Vec<[u8; 32]>>,
-    pub images: Vec<[u8; 32]>,
+    pub rings: Vec<Vec<PublicKey<G>>>,
+    pub images: Vec<KeyImage<G>>,
+    pub version: Version,
 }
 impl CLSAG {
-    pub fn sign<Hash: Digest<OutputSize = U64> + Clone>(
+    pub fn legacy(
+        challenge: [u8; 32],
+        response: Vec<[u8; 32]>,
+        rings: Vec<Vec<PublicKey>>,
+        images: Vec<KeyImage>,
+    ) -> CLSAG {
+        CLSAG {
+            challenge,
+            response,
+            rings,
+            images,
+            version: Version::Legacy,
+        }
+    }
+}
+impl<G: Group> CLSAG<G> {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        data: impl AsRef<[u8]>,
+    ) -> Result<CLSAG<G>, Error> {
+        CLSAG::sign_with_context::<Hash>(rng, secrets, rings, b"", data)
+    }
+    pub fn sign_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<CLSAG<G>, Error> {
+        CLSAG::sign_with_version::<Hash>(rng, secrets, rings, Version::V2, context, data)
+    }
+    pub fn sign_with_version<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        mut rings: Vec<Vec<PublicKey<G>>>,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<CLSAG<G>, Error> {
+        let secret_index = rng.gen_range(0..=rings.len());
+        rings.insert(
+            secret_index,
+            secrets.iter().map(SecretKey::public_key).collect(),
+        );
+        CLSAG::sign_with_index::<Hash>(rng, secrets, rings, secret_index, version, context, data)
+    }
+    pub fn sign_canonical<Hash: RingHash>(
         rng: &mut impl CryptoRngCore,
-        secrets: &[Scalar],
-        mut rings: Vec<Vec<RistrettoPoint>>,
+        secrets: &[SecretKey<G>],
+        mut rings: Vec<Vec<PublicKey<G>>>,
+        context: &[u8],
         data: impl AsRef<[u8]>,
-    ) -> Option<CLSAG> {
-        let images = images::<Hash>(secrets);
-        let public_points = secrets
+    ) -> Result<CLSAG<G>, Error> {
+        let public_keys = secrets
             .iter()
-            .map(|scalar| scalar * RISTRETTO_BASEPOINT_POINT)
+            .map(SecretKey::public_key)
             .collect::<Vec<_>>();
-        let base_point = point_hash::<Hash>(public_points[0]);
+        rings.push(public_keys.clone());
+        keys::canonicalize(&mut rings);
+        keys::validate(rings.iter().map(Vec::as_slice))?;
+        let secret_index = rings.iter().position(|ring| *ring == public_keys).unwrap();
+        CLSAG::sign_with_index::<Hash>(
+            rng,
+            secrets,
+            rings,
+            secret_index,
+            Version::V2,
+            context,
+            data,
+        )
+    }
+    pub fn sign_with_index<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<CLSAG<G>, Error> {
+        CLSAG::sign_members::<Hash>(
+            rng,
+            secrets,
+            rings,
+            secret_index,
+            version,
+            context,
+            data,
+            |members| members.hash::<Hash>(version, 1),
+        )
+    }
+    pub fn sign_with_tag<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        tag: &[u8],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<CLSAG<G>, Error> {
+        CLSAG::sign_with_tag_and_version::<Hash>(
+            rng,
+            secrets,
+            rings,
+            tag,
+            Version::V2,
+            context,
+            data,
+        )
+    }
+    pub fn sign_with_tag_and_version<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        mut rings: Vec<Vec<PublicKey<G>>>,
+        tag: &[u8],
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<CLSAG<G>, Error> {
         let secret_index = rng.gen_range(0..=rings.len());
-        rings.insert(secret_index, public_points);
+        rings.insert(
+            secret_index,
+            secrets.iter().map(SecretKey::public_key).collect(),
+        );
+        CLSAG::sign_members::<Hash>(
+            rng,
+            secrets,
+            rings,
+            secret_index,
+            version,
+            context,
+            data,
+            |members| members.hash_tagged::<Hash>(version, tag, 1),
+        )
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign_with_index_and_cache<Hash: RingHash + 'static>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<CLSAG<G>, Error> {
+        CLSAG::sign_members::<Hash>(
+            rng,
+            secrets,
+            rings,
+            secret_index,
+            version,
+            context,
+            data,
+            |members| members.hash_cached::<Hash>(version, 1, cache),
+        )
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn sign_members<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        hash: impl FnOnce(Members<G>) -> Members<G>,
+    ) -> Result<CLSAG<G>, Error> {
+        supported_version(version)?;
+        if secrets.is_empty() {
+            return Err(Error::EmptySecrets);
+        }
+        if rings.iter().any(|ring| ring.len() != secrets.len()) {
+            return Err(Error::RingWidthMismatch);
+        }
+        if secret_index >= rings.len() {
+            return Err(Error::SignerIndexOutOfRange);
+        }
+        let public_keys = secrets
+            .iter()
+            .map(SecretKey::public_key)
+            .collect::<Vec<_>>();
+        if rings[secret_index] != public_keys {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let members = hash(Members::new(rings.iter().map(Vec::as_slice))?);
+        let base_point = members.hashes(secret_index)[0];
+        let images = secrets
+            .iter()
+            .map(|secret| KeyImage(base_point * secret.0))
+            .collect::<Vec<_>>();
         let x = rings.len();
-        let y = rings[0].len();
-        let prefixed_hashes_with_images =
-            CLSAG::prefixed_hashes_with_images::<Hash>(&rings, &images);
+        let coefficients =
+            CLSAG::aggregation_coefficients::<Hash>(version, context, &members.bytes, &images)?;
         let aggregate_private_key =
-            CLSAG::aggregate_private_key(&rings, &prefixed_hashes_with_images, secrets);
-        let aggregate_public_keys =
-            CLSAG::aggregate_public_keys(&rings, &prefixed_hashes_with_images);
-        let aggregate_image =
-            CLSAG::aggregate_image::<Hash>(&rings, &prefixed_hashes_with_images, &images);
-        let mut hashes = (0..x)
-            .map(|_| {
-                let mut hash = Hash::new();
-                for i in 0..x {
-                    for j in 0..y {
-                        hash.update(rings[i][j].compress().as_bytes());
-                    }
-                }
-                hash.update(&data);
-                hash
-            })
-            .collect::<Vec<_>>();
-        let secret_scalar = scalar_random(rng);
+            Zeroizing::new(CLSAG::aggregate_private_key(&coefficients, secrets));
+        let aggregate_public_keys = CLSAG::aggregate_public_keys(&members, &coefficients);
+        let aggregate_image = CLSAG::aggregate_image(&coefficients, &images);
+        let transcript = CLSAG::transcript::<Hash>(version, context, &members, &images, data)?;
+        let mut transcripts = (0..x).map(|_| transcript.clone()).collect::<Vec<_>>();
+        let secret_scalar = Zeroizing::new(G::scalar_random(rng));
         let mut current_index = (secret_index + 1) % x;
-        hashes[current_index].update(
-            (secret_scalar * RISTRETTO_BASEPOINT_POINT)
-                .compress()
-                .as_bytes(),
-        );
-        hashes[current_index].update((secret_scalar * base_point).compress().as_bytes());
-        let mut challenges = vec![scalar_zero(); x];
-        challenges[current_index] = scalar_from_hash(hashes[current_index].clone());
-        let mut response = (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+        transcripts[current_index].append_element::<G>(b"L", &(G::generator() * *secret_scalar));
+        transcripts[current_index].append_element::<G>(b"R", &(base_point * *secret_scalar));
+        let mut challenges = vec![G::scalar_from_u64(0); x];
+        challenges[current_index] = transcripts[current_index].challenge::<G>(b"c");
+        let mut response = (0..x).map(|_| G::scalar_random(rng)).collect::<Vec<_>>();
         loop {
             let next_index = (current_index + 1) % x;
-            hashes[next_index].update(
-                RistrettoPoint::multiscalar_mul(
+            transcripts[next_index].append_element::<G>(
+                b"L",
+                &G::multiscalar_mul(
                     &[response[current_index % x], challenges[current_index % x]],
-                    &[
-                        RISTRETTO_BASEPOINT_POINT,
-                        aggregate_public_keys[current_index % x],
-                    ],
-                )
-                .compress()
-                .as_bytes(),
+                    &[G::generator(), aggregate_public_keys[current_index % x]],
+                ),
             );
-            hashes[next_index].update(
-                RistrettoPoint::multiscalar_mul(
+            transcripts[next_index].append_element::<G>(
+                b"R",
+                &G::multiscalar_mul(
                     &[response[current_index % x], challenges[current_index % x]],
-                    &[
-                        point_hash::<Hash>(rings[current_index % x][0]),
-                        aggregate_image,
-                    ],
-                )
-                .compress()
-                .as_bytes(),
+                    &[members.hashes(current_index % x)[0], aggregate_image],
+                ),
             );
-            challenges[next_index] = scalar_from_hash(hashes[next_index].clone());
+            challenges[next_index] = transcripts[next_index].challenge::<G>(b"c");
             if (secret_index >= 1 && current_index % x == (secret_index - 1) % x)
                 || (secret_index == 0 && current_index % x == x - 1)
             {
@@ -102,84 +263,137 @@ impl CLSAG {
             }
             current_index = next_index;
         }
-        response[secret_index] = secret_scalar - (challenges[secret_index] * aggregate_private_key);
-        Some(CLSAG {
-            challenge: challenges[0].to_bytes(),
-            response: response.iter().map(|scalar| scalar.to_bytes()).collect(),
-            rings: rings
-                .iter()
-                .map(|vec| {
-                    vec.iter()
-                        .map(|point| point.compress().to_bytes())
-                        .collect()
-                })
-                .collect::<Vec<Vec<_>>>(),
-            images: images
-                .iter()
-                .map(|point| point.compress().to_bytes())
-                .collect(),
+        response[secret_index] =
+            *secret_scalar - *Zeroizing::new(challenges[secret_index] * *aggregate_private_key);
+        Ok(CLSAG {
+            challenge: G::scalar_to_bytes(&challenges[0]),
+            response: response.iter().map(G::scalar_to_bytes).collect(),
+            rings,
+            images,
+            version,
         })
     }
-    pub fn verify<Hash: Digest<OutputSize = U64> + Clone>(&self, data: impl AsRef<[u8]>) -> bool {
-        match || -> Option<bool> {
-            let x = self.rings.len();
-            let y = self.rings[0].len();
-            let rings = self
-                .rings
-                .iter()
-                .map(|vec| vec.iter().map(|bytes| point_from_slice(bytes)).collect())
-                .collect::<Option<Vec<Vec<_>>>>()?;
-            let images = self
-                .images
-                .iter()
-                .map(|bytes| point_from_slice(bytes))
-                .collect::<Option<Vec<_>>>()?;
-            let response = self
-                .response
-                .iter()
-                .map(|&bytes| scalar_from_canonical(bytes))
-                .collect::<Option<Vec<_>>>()?;
-            let challenge_0 = scalar_from_canonical(self.challenge)?;
-            let mut challenge_1 = challenge_0;
-            let prefixed_hashes_with_images =
-                CLSAG::prefixed_hashes_with_images::<Hash>(&rings, &images);
-            let aggregate_public_keys =
-                CLSAG::aggregate_public_keys(&rings, &prefixed_hashes_with_images);
-            let aggregate_image =
-                CLSAG::aggregate_image::<Hash>(&rings, &prefixed_hashes_with_images, &images);
-            for i in 0..x {
-                let mut hash: Hash = Hash::new();
-                for j in 0..x {
-                    for k in 0..y {
-                        hash.update(rings[j][k].compress().as_bytes());
-                    }
-                }
-                hash.update(&data);
-                hash.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[response[i], challenge_1],
-                        &[RISTRETTO_BASEPOINT_POINT, aggregate_public_keys[i]],
-                    )
-                    .compress()
-                    .as_bytes(),
-                );
-                hash.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[response[i], challenge_1],
-                        &[point_hash::<Hash>(rings[i][0]), aggregate_image],
-                    )
-                    .compress()
-                    .as_bytes(),
-                );
-                challenge_1 = scalar_from_hash(hash);
-            }
-            Some(challenge_0 == challenge_1)
-        }() {
-            Some(x) => x,
-            None => false,
+    pub fn verify<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(b"", data)
+    }
+    pub fn verify_legacy<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        legacy_version(self.version)?;
+        CLSAG::verify_transcript::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.rings.iter().map(Vec::as_slice))?.hash::<Hash>(self.version, 1),
+            &self.images,
+            self.version,
+            b"",
+            data,
+        )
+    }
+    pub fn verify_canonical<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if !keys::is_canonical(self.rings.iter().map(Vec::as_slice)) {
+            return Err(Error::NonCanonicalRing);
         }
+        keys::validate(self.rings.iter().map(Vec::as_slice))?;
+        self.verify_with_context::<Hash>(context, data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        CLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.rings.iter().map(Vec::as_slice))?.hash::<Hash>(self.version, 1),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_with_tag<Hash: RingHash>(
+        &self,
+        tag: &[u8],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        CLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.rings.iter().map(Vec::as_slice))?.hash_tagged::<Hash>(
+                self.version,
+                tag,
+                1,
+            ),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_with_cache<Hash: RingHash + 'static>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Error> {
+        CLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.rings.iter().map(Vec::as_slice))?.hash_cached::<Hash>(
+                self.version,
+                1,
+                cache,
+            ),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_batch<Hash: RingHash + 'static>(
+        batch: &[(&CLSAG<G>, &[u8])],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        CLSAG::verify_batch_with_context::<Hash>(batch, b"")
+    }
+    pub fn verify_batch_with_context<Hash: RingHash + 'static>(
+        batch: &[(&CLSAG<G>, &[u8])],
+        context: &[u8],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        CLSAG::verify_batch_with_cache::<Hash>(batch, context, &mut HashMap::new())
+    }
+    pub fn verify_batch_with_cache<Hash: RingHash + 'static>(
+        batch: &[(&CLSAG<G>, &[u8])],
+        context: &[u8],
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Vec<(usize, Error)>> {
+        members::verify_batch::<_, Hash, _>(
+            batch,
+            cache,
+            |clsag| {
+                Ok((
+                    Members::new(clsag.rings.iter().map(Vec::as_slice))?,
+                    clsag.version,
+                    1,
+                ))
+            },
+            |clsag, members, data| {
+                CLSAG::verify_parts::<Hash>(
+                    clsag.challenge,
+                    &clsag.response,
+                    members,
+                    &clsag.images,
+                    clsag.version,
+                    context,
+                    data,
+                )
+            },
+        )
     }
-    pub fn link(images: &[&[[u8; 32]]]) -> bool {
+    pub fn link(images: &[&[KeyImage<G>]]) -> bool {
         if images.is_empty() || images[0].is_empty() {
             return false;
         }
@@ -188,81 +402,272 @@ impl CLSAG {
             .skip(1)
             .all(|&slice| !slice.is_empty() && slice[0] == images[0][0])
     }
-    fn prefixed_hashes_with_images<Hash: Digest<OutputSize = U64>>(
-        rings: &Vec<Vec<RistrettoPoint>>,
-        images: &Vec<RistrettoPoint>,
-    ) -> Vec<Hash> {
+    pub fn link_with_tags<Hash: RingHash>(
+        signatures: &[(&CLSAG<G>, &[u8], &[u8])],
+        context: &[u8],
+    ) -> Result<bool, Error> {
+        for (signature, tag, data) in signatures {
+            signature.verify_with_tag::<Hash>(tag, context, data)?;
+        }
+        if signatures.is_empty() {
+            return Ok(false);
+        }
+        let (first, first_tag, _) = signatures[0];
+        if signatures.iter().any(|(_, tag, _)| *tag != first_tag) {
+            return Err(Error::TagMismatch);
+        }
+        Ok(signatures
+            .iter()
+            .skip(1)
+            .all(|(signature, _, _)| signature.images[0] == first.images[0]))
+    }
+    fn domain(tag: &str) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[..tag.len()].copy_from_slice(tag.as_bytes());
+        bytes
+    }
+    pub fn detach(self) -> (DetachedCLSAG<G>, Vec<Vec<PublicKey<G>>>) {
+        (
+            DetachedCLSAG {
+                challenge: self.challenge,
+                response: self.response,
+                images: self.images,
+                version: self.version,
+            },
+            self.rings,
+        )
+    }
+    fn verify_parts<Hash: RingHash>(
+        challenge: [u8; 32],
+        response: &[[u8; 32]],
+        rings: &Members<G>,
+        images: &[KeyImage<G>],
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        supported_version(version)?;
+        CLSAG::verify_transcript::<Hash>(challenge, response, rings, images, version, context, data)
+    }
+    fn verify_transcript<Hash: RingHash>(
+        challenge: [u8; 32],
+        response: &[[u8; 32]],
+        rings: &Members<G>,
+        images: &[KeyImage<G>],
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
         let x = rings.len();
-        let y = rings[0].len();
-        (0..y)
-            .map(|_| {
-                let mut hash = Hash::new();
-                for j in 0..x {
-                    for k in 0..y {
-                        hash.update(rings[j][k].compress().as_bytes());
-                    }
+        if images.len() != rings.width {
+            return Err(Error::ImageCountMismatch);
+        }
+        if response.len() != x {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let response = response
+            .iter()
+            .enumerate()
+            .map(|(index, &bytes)| {
+                G::scalar_from_canonical(bytes).ok_or(Error::NonCanonicalScalar { index })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let challenge_0 =
+            G::scalar_from_canonical(challenge).ok_or(Error::NonCanonicalChallenge)?;
+        let mut challenge_1 = challenge_0;
+        let coefficients =
+            CLSAG::aggregation_coefficients::<Hash>(version, context, &rings.bytes, images)?;
+        let aggregate_public_keys = CLSAG::aggregate_public_keys(rings, &coefficients);
+        let aggregate_image = CLSAG::aggregate_image(&coefficients, images);
+        let transcript = CLSAG::transcript::<Hash>(version, context, rings, images, data)?;
+        for i in 0..x {
+            let mut transcript = transcript.clone();
+            transcript.append_element::<G>(
+                b"L",
+                &G::vartime_multiscalar_mul(
+                    &[response[i], challenge_1],
+                    &[G::generator(), aggregate_public_keys[i]],
+                ),
+            );
+            transcript.append_element::<G>(
+                b"R",
+                &G::vartime_multiscalar_mul(
+                    &[response[i], challenge_1],
+                    &[rings.hashes(i)[0], aggregate_image],
+                ),
+            );
+            challenge_1 = transcript.challenge::<G>(b"c");
+        }
+        if challenge_0 != challenge_1 {
+            return Err(Error::ChallengeMismatch);
+        }
+        Ok(())
+    }
+    fn transcript<Hash: RingHash>(
+        version: Version,
+        context: &[u8],
+        rings: &Members<G>,
+        images: &[KeyImage<G>],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript = Transcript::versioned(version, b"ring-signature/CLSAG/v2", context)?;
+        if version != Version::Legacy {
+            transcript.append_message(b"dom-sep", CLSAG::<G>::domain("CLSAG_round"));
+        }
+        if let Some(tag) = &rings.tag {
+            transcript.append_message(b"tag", tag);
+        }
+        for key in &rings.bytes {
+            transcript.append_encoding::<G>(b"ring", key);
+        }
+        if matches!(version, Version::V2 | Version::V3) {
+            for image in images {
+                transcript.append_element::<G>(b"image", &image.0);
+            }
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+    fn aggregation_coefficients<Hash: RingHash>(
+        version: Version,
+        context: &[u8],
+        rings: &[G::Encoding],
+        images: &[KeyImage<G>],
+    ) -> Result<Vec<G::Scalar>, Error> {
+        let transcript =
+            Transcript::<Hash>::versioned(version, b"ring-signature/CLSAG/v2/agg", context)?;
+        Ok((0..images.len())
+            .map(|j| {
+                let mut transcript = transcript.clone();
+                if version != Version::Legacy {
+                    transcript.append_message(
+                        b"dom-sep",
+                        CLSAG::<G>::domain(&format!("CLSAG_agg_{}", j)),
+                    );
+                }
+                for key in rings {
+                    transcript.append_encoding::<G>(b"ring", key);
                 }
-                for j in 0..y {
-                    hash.update(images[j].compress().as_bytes());
+                for image in images {
+                    transcript.append_element::<G>(b"image", &image.0);
                 }
-                hash
+                transcript.challenge::<G>(b"mu")
             })
-            .collect()
+            .collect())
     }
-    fn aggregate_private_key<Hash: Digest<OutputSize = U64> + Clone>(
-        rings: &Vec<Vec<RistrettoPoint>>,
-        prefixed_hashes_with_images: &Vec<Hash>,
-        secrets: &[Scalar],
-    ) -> Scalar {
-        let y = rings[0].len();
-        (0..y)
-            .map(|i| scalar_from_hash(prefixed_hashes_with_images[i].clone()) * secrets[i])
-            .sum()
-    }
-    fn aggregate_public_keys<Hash: Digest<OutputSize = U64> + Clone>(
-        rings: &Vec<Vec<RistrettoPoint>>,
-        prefixed_hashes_with_images: &Vec<Hash>,
-    ) -> Vec<RistrettoPoint> {
-        let x = rings.len();
-        let y = rings[0].len();
-        (0..x)
-            .map(|i| {
-                (0..y)
-                    .map(|j| scalar_from_hash(prefixed_hashes_with_images[j].clone()) * rings[i][j])
-                    .sum()
+    fn aggregate_private_key(coefficients: &[G::Scalar], secrets: &[SecretKey<G>]) -> G::Scalar {
+        coefficients
+            .iter()
+            .zip(secrets)
+            .fold(G::scalar_from_u64(0), |sum, (coefficient, secret)| {
+                sum + *Zeroizing::new(*coefficient * secret.0)
             })
+    }
+    fn aggregate_public_keys(rings: &Members<G>, coefficients: &[G::Scalar]) -> Vec<G::Point> {
+        iter!(0..rings.len())
+            .map(|i| G::vartime_multiscalar_mul(coefficients, rings.row(i)))
             .collect()
     }
-    fn aggregate_image<Hash: Digest<OutputSize = U64> + Clone>(
-        rings: &Vec<Vec<RistrettoPoint>>,
-        prefixed_hashes_with_images: &Vec<Hash>,
-        images: &Vec<RistrettoPoint>,
-    ) -> RistrettoPoint {
-        let y = rings[0].len();
-        (0..y)
-            .map(|i| scalar_from_hash(prefixed_hashes_with_images[i].clone()) * images[i])
-            .sum()
+    fn aggregate_image(coefficients: &[G::Scalar], images: &[KeyImage<G>]) -> G::Point {
+        G::vartime_multiscalar_mul(
+            coefficients,
+            &images.iter().map(|image| image.0).collect::<Vec<_>>(),
+        )
+    }
+}
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct DetachedCLSAG<G: Group = Ristretto255> {
+    pub challenge: [u8; 32],
+    pub response: Vec<[u8; 32]>,
+    pub images: Vec<KeyImage<G>>,
+    pub version: Version,
+}
+impl<G: Group> DetachedCLSAG<G> {
+    pub fn attach(self, rings: Vec<Vec<PublicKey<G>>>) -> CLSAG<G> {
+        CLSAG {
+            challenge: self.challenge,
+            response: self.response,
+            rings,
+            images: self.images,
+            version: self.version,
+        }
+    }
+    pub fn verify_with_ring<Hash: RingHash>(
+        &self,
+        rings: &[Vec<PublicKey<G>>],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        self.verify_with_ring_and_context::<Hash>(rings, b"", data)
+    }
+    pub fn verify_with_ring_and_context<Hash: RingHash>(
+        &self,
+        rings: &[Vec<PublicKey<G>>],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        CLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(rings.iter().map(Vec::as_slice))?.hash::<Hash>(self.version, 1),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_with_ring_and_cache<Hash: RingHash + 'static>(
+        &self,
+        rings: &[Vec<PublicKey<G>>],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Error> {
+        CLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(rings.iter().map(Vec::as_slice))?.hash_cached::<Hash>(
+                self.version,
+                1,
+                cache,
+            ),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::point_random;
+    use crate::LruCache;
+    use crate::RistrettoPoint;
+    use crate::Wide;
+    use crate::Xof;
     use lazy_static::lazy_static;
     use rand_core::OsRng;
     use sha2::Sha512;
+    use sha3::Keccak256;
+    use sha3::Sha3_512;
+    use sha3::Shake256;
     const DATA_0: &[u8] = b"hello from";
     const DATA_1: &str = "zudo";
     const X: usize = 2;
     const Y: usize = 2;
+    const LEGACY: &str = "45b00deb490aabb5e340b7cb49b13b0fcdb2ee044e821a547d70960daf63a70802000000000000003f2ddb0b710763bab025027cb6e358217a376ed6d16154cda02bae4efd51ea0009d788f1eb833cbe0298a86d691a17851230b4b60aa0718e5907df2fbb6327060200000000000000020000000000000010412aba80872a2de5b22505a96fb6543c0f361842f929158021dd06ac290626ee0ba6d2cc9ce0e78817710e0dfd55771f342f03fc7c4c50f13cbe58719f02450200000000000000c0db4d49c9ad7dfe0701beffbae1e3846cf22fc6c34c915f052408d51bc5ad485cf293697cc70839fa65663ff56df80d87593c3c78a5ee6c31c2828ad3a5dd080200000000000000528cfa97243ce5f6c4e57ada629b4c42c8f1f331902416ce234e6412e30c0b5d3e496f67e1df5c0e5d25e8da2b7f04df2202bdf51b811f7d1221405909e9f864";
+    const V1: &str = "8ff81a17b323b4675e3e97ebe74574ad87b7b153e2d352983ece5b5c70f4100c0200000000000000cd8dbe1253c3fe52713c6cb084ef73198f31efbe99b900e09c556dc18c11ec0ecb846940c3f0cb2c8e1023726e92428eb0efd99b112a473b217e803c1cbc4b0102000000000000000200000000000000dc5de455a3afe19eafbc839964f119f17386f7c91ba1b5a574b224bb52c4cf45c80af806b6554a70bc90cf533f2730245c68eb8799d9bd81d5f9e47adb16473f0200000000000000005158ca54617ac50cb3405a8b862991cd0b69109d2420c3d0db1b0fb45dfc3bf6e9b975c5711606cf525571802c05032df79e0af73997dd245c87ee9c76321d02000000000000009c57dfb1553171ac2d8aaa8872a445d1f6496d904174d4919363529fad2c6d5ae6b65d311c6d30a578fe5a67a2fe78dc8241838a081f7e85130361b0e239f86001000000";
     lazy_static! {
-        static ref SECRETS_0: Vec<Scalar> = (0..Y).map(|_| scalar_random(&mut OsRng)).collect();
-        static ref SECRETS_1: Vec<Scalar> = (0..Y).map(|_| scalar_random(&mut OsRng)).collect();
-        static ref RINGS_0: Vec<Vec<RistrettoPoint>> = (0..X)
-            .map(|_| (0..Y).map(|_| point_random(&mut OsRng)).collect())
+        static ref SECRETS_0: Vec<SecretKey> =
+            (0..Y).map(|_| SecretKey::random(&mut OsRng)).collect();
+        static ref SECRETS_1: Vec<SecretKey> =
+            (0..Y).map(|_| SecretKey::random(&mut OsRng)).collect();
+        static ref RINGS_0: Vec<Vec<PublicKey>> = (0..X)
+            .map(|_| (0..Y).map(|_| point_random(&mut OsRng).into()).collect())
             .collect();
-        static ref RINGS_1: Vec<Vec<RistrettoPoint>> = (0..X)
-            .map(|_| (0..Y).map(|_| point_random(&mut OsRng)).collect())
+        static ref RINGS_1: Vec<Vec<PublicKey>> = (0..X)
+            .map(|_| (0..Y).map(|_| point_random(&mut OsRng).into()).collect())
             .collect();
     }
     #[test]
@@ -272,10 +677,235 @@ mod tests {
         let b = CLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_1.clone(), DATA_0).unwrap();
         let c = CLSAG::sign::<Sha512>(rng, &SECRETS_1, RINGS_0.clone(), DATA_0).unwrap();
         let d = CLSAG::sign::<Sha512>(rng, &SECRETS_1, RINGS_1.clone(), DATA_0).unwrap();
-        assert!((a.verify::<Sha512>(DATA_0)));
-        assert!((b.verify::<Sha512>(DATA_0)));
-        assert!((c.verify::<Sha512>(DATA_0)));
-        assert!((d.verify::<Sha512>(DATA_0)));
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+        assert!(c.verify::<Sha512>(DATA_0).is_ok());
+        assert!(d.verify::<Sha512>(DATA_0).is_ok());
+    }
+    #[test]
+    fn sign_errors() {
+        let rng = &mut OsRng;
+        assert_eq!(
+            CLSAG::sign::<Sha512>(rng, &[], RINGS_0.clone(), DATA_0),
+            Err(Error::EmptySecrets)
+        );
+        assert_eq!(
+            CLSAG::sign::<Sha512>(rng, &SECRETS_0[..1], RINGS_0.clone(), DATA_0),
+            Err(Error::RingWidthMismatch)
+        );
+    }
+    #[test]
+    fn sign_with_index() {
+        let rng = &mut OsRng;
+        let mut rings = RINGS_0.clone();
+        rings.insert(1, SECRETS_0.iter().map(SecretKey::public_key).collect());
+        let a = CLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings.clone(),
+            1,
+            Version::V2,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert_eq!(a.rings, rings);
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            CLSAG::sign_with_index::<Sha512>(
+                rng,
+                &SECRETS_0,
+                rings.clone(),
+                0,
+                Version::V2,
+                b"",
+                DATA_0
+            ),
+            Err(Error::SignerKeyMismatch)
+        );
+        assert_eq!(
+            CLSAG::sign_with_index::<Sha512>(
+                rng,
+                &SECRETS_0,
+                rings,
+                X + 1,
+                Version::V2,
+                b"",
+                DATA_0
+            ),
+            Err(Error::SignerIndexOutOfRange)
+        );
+    }
+    #[test]
+    fn canonical() {
+        let rng = &mut OsRng;
+        let a =
+            CLSAG::sign_canonical::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), b"", DATA_0).unwrap();
+        assert!(keys::is_canonical(a.rings.iter().map(Vec::as_slice)));
+        assert!(a.verify_canonical::<Sha512>(b"", DATA_0).is_ok());
+        let public_keys = SECRETS_0
+            .iter()
+            .map(SecretKey::public_key)
+            .collect::<Vec<_>>();
+        let mut rings = a.rings.clone();
+        rings.reverse();
+        let index = rings.iter().position(|ring| *ring == public_keys).unwrap();
+        let b = CLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings,
+            index,
+            Version::V2,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            b.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::NonCanonicalRing)
+        );
+        let mut c = a.clone();
+        c.rings[0] = c.rings[1].clone();
+        assert_eq!(
+            c.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::NonCanonicalRing)
+        );
+        let mut rings = RINGS_0.clone();
+        rings.push(RINGS_0[0].clone());
+        rings.push(public_keys.clone());
+        assert!(CLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings,
+            X + 1,
+            Version::V2,
+            b"",
+            DATA_0
+        )
+        .is_ok());
+        let mut rings = RINGS_0.clone();
+        rings.push(RINGS_0[0].clone());
+        assert!(matches!(
+            CLSAG::sign_canonical::<Sha512>(rng, &SECRETS_0, rings, b"", DATA_0),
+            Err(Error::DuplicateMember { .. })
+        ));
+        let mut rings = RINGS_0.clone();
+        rings[1][1] = RistrettoPoint::default().into();
+        assert!(matches!(
+            CLSAG::sign_canonical::<Sha512>(rng, &SECRETS_0, rings.clone(), b"", DATA_0),
+            Err(Error::IdentityMember { col: 1, .. })
+        ));
+        rings.push(public_keys.clone());
+        keys::canonicalize(&mut rings);
+        let index = rings.iter().position(|ring| *ring == public_keys).unwrap();
+        let d = CLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings,
+            index,
+            Version::V2,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(d.verify::<Sha512>(DATA_0).is_ok());
+        assert!(matches!(
+            d.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::IdentityMember { col: 1, .. })
+        ));
+    }
+    #[test]
+    fn detach() {
+        let rng = &mut OsRng;
+        let a = CLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        let (b, rings) = a.clone().detach();
+        assert!(b.verify_with_ring::<Sha512>(&rings, DATA_0).is_ok());
+        assert!(bincode::serialize(&b).unwrap().len() < bincode::serialize(&a).unwrap().len());
+        let mut reversed = rings.clone();
+        reversed.reverse();
+        assert_eq!(
+            b.verify_with_ring::<Sha512>(&reversed, DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(b.attach(rings), a);
+    }
+    #[test]
+    fn verify_batch() {
+        let rng = &mut OsRng;
+        let a = CLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        let b = CLSAG::sign::<Sha512>(rng, &SECRETS_1, RINGS_0.clone(), DATA_0).unwrap();
+        let c = CLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_1.clone(), DATA_0).unwrap();
+        assert!(CLSAG::verify_batch::<Sha512>(&[(&a, DATA_0), (&b, DATA_0), (&c, DATA_0)]).is_ok());
+        let mut d = b.clone();
+        d.challenge = [0xff; 32];
+        assert_eq!(
+            CLSAG::verify_batch::<Sha512>(&[(&a, DATA_0), (&d, DATA_0), (&c, DATA_1.as_bytes())]),
+            Err(vec![
+                (1, Error::NonCanonicalChallenge),
+                (2, Error::ChallengeMismatch)
+            ])
+        );
+    }
+    #[test]
+    fn cache() {
+        let rng = &mut OsRng;
+        let mut cache = LruCache::new(64);
+        let mut rings = RINGS_0.clone();
+        rings.push(SECRETS_0.iter().map(SecretKey::public_key).collect());
+        let a = CLSAG::sign_with_index_and_cache::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings.clone(),
+            X,
+            Version::V2,
+            b"",
+            DATA_0,
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(cache.len(), X + 1);
+        assert!(a
+            .verify_with_cache::<Sha512>(b"", DATA_0, &mut cache)
+            .is_ok());
+        let b = CLSAG::sign_with_index_and_cache::<Sha3_512>(
+            rng,
+            &SECRETS_0,
+            rings,
+            X,
+            Version::V2,
+            b"",
+            DATA_0,
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(cache.len(), 2 * (X + 1));
+        assert!(b.verify::<Sha3_512>(DATA_0).is_ok());
+        assert!(a
+            .verify_with_cache::<Sha512>(b"", DATA_0, &mut cache)
+            .is_ok());
+        assert_eq!(
+            b.verify_with_cache::<Sha512>(b"", DATA_0, &mut cache),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let rng = &mut OsRng;
+        let a = CLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        assert_eq!(a.verify::<Sha512>(DATA_1), Err(Error::ChallengeMismatch));
+        let mut b = a.clone();
+        b.response.pop();
+        assert_eq!(
+            b.verify::<Sha512>(DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut c = a.clone();
+        c.images.pop();
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::ImageCountMismatch));
+        let mut d = a.clone();
+        d.rings = vec![];
+        assert_eq!(d.verify::<Sha512>(DATA_0), Err(Error::EmptyRing));
     }
     #[test]
     fn link() {
@@ -292,4 +922,253 @@ mod tests {
         assert!((!CLSAG::link(&[&a.images, &e.images])));
         assert!((!CLSAG::link(&[&a.images, &f.images])));
     }
+    #[test]
+    #[allow(deprecated)]
+    fn legacy() {
+        let (challenge, response, rings, images) =
+            bincode::deserialize(&hex::decode(LEGACY).unwrap()).unwrap();
+        let a = CLSAG::legacy(challenge, response, rings, images);
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        assert!(a.verify_legacy::<Sha512>(DATA_0).is_ok());
+        let mut b = a.clone();
+        b.version = Version::V1;
+        assert_eq!(
+            b.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        let mut tampered = a.clone();
+        tampered.images[0] = SECRETS_1[0].image::<Sha512>();
+        assert_eq!(
+            tampered.verify::<Sha512>(DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(
+            CLSAG::verify_batch::<Sha512>(&[(&tampered, DATA_0)]),
+            Err(vec![(0, Error::UnsupportedVersion)])
+        );
+        let rng = &mut OsRng;
+        let mut c = CLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        c.version = Version::Legacy;
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        let (detached, rings) = c.clone().detach();
+        assert_eq!(
+            detached.verify_with_ring::<Sha512>(&rings, DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(
+            c.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        for version in [Version::Legacy, Version::V1] {
+            assert_eq!(
+                CLSAG::sign_with_version::<Sha512>(
+                    rng,
+                    &SECRETS_0,
+                    RINGS_0.clone(),
+                    version,
+                    b"",
+                    DATA_0
+                ),
+                Err(Error::UnsupportedVersion)
+            );
+        }
+    }
+    #[test]
+    #[allow(deprecated)]
+    fn aggregation_coefficients() {
+        let images = SECRETS_0
+            .iter()
+            .map(|secret| secret.image::<Sha512>())
+            .collect::<Vec<_>>();
+        let rings = RINGS_0
+            .iter()
+            .flatten()
+            .map(PublicKey::to_bytes)
+            .collect::<Vec<_>>();
+        let coefficients = |version| {
+            CLSAG::aggregation_coefficients::<Sha512>(version, b"", &rings, &images).unwrap()
+        };
+        let legacy = coefficients(Version::Legacy);
+        let v1 = coefficients(Version::V1);
+        let v2 = coefficients(Version::V2);
+        assert_eq!(legacy[0], legacy[1]);
+        assert_ne!(v1[0], v1[1]);
+        assert_ne!(v2[0], v2[1]);
+        assert_ne!(v1[0], v2[0]);
+        let mut transcript = Transcript::<Sha512>::new(b"ring-signature/CLSAG/v2/agg");
+        transcript.append_message(b"context", b"");
+        transcript.append_message(b"dom-sep", CLSAG::<Ristretto255>::domain("CLSAG_agg_1"));
+        for key in &rings {
+            transcript.append_message(b"ring", key);
+        }
+        for image in &images {
+            transcript.append_message(b"image", image.to_bytes());
+        }
+        assert_eq!(v2[1], transcript.challenge_scalar(b"mu"));
+    }
+    #[test]
+    #[allow(deprecated)]
+    fn context() {
+        let a: CLSAG = bincode::deserialize(&hex::decode(V1).unwrap()).unwrap();
+        assert_eq!(a.version, Version::V1);
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        assert!(a.verify_legacy::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            a.verify_with_context::<Sha512>(b"zudo", DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        let rng = &mut OsRng;
+        let b =
+            CLSAG::sign_with_context::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), b"zudo", DATA_0)
+                .unwrap();
+        assert_eq!(b.version, Version::V2);
+        assert!(b.verify_with_context::<Sha512>(b"zudo", DATA_0).is_ok());
+        assert_eq!(
+            b.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(b.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+        assert_eq!(
+            b.verify_with_context::<Sha512>(b"odu", DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn version() {
+        let rng = &mut OsRng;
+        let a = CLSAG::sign_with_version::<Sha512>(
+            rng,
+            &SECRETS_0,
+            RINGS_0.clone(),
+            Version::V3,
+            b"zudo",
+            DATA_0,
+        )
+        .unwrap();
+        assert_eq!(a.version, Version::V3);
+        assert!(a.verify_with_context::<Sha512>(b"zudo", DATA_0).is_ok());
+        let image = SECRETS_0[0].image_with_version::<Sha512>(Version::V3);
+        assert_eq!(a.images[0], image);
+        assert_ne!(a.images[0], SECRETS_0[0].image::<Sha512>());
+        let (detached, rings) = a.clone().detach();
+        assert!(detached
+            .verify_with_ring_and_cache::<Sha512>(
+                &rings,
+                b"zudo",
+                DATA_0,
+                &mut LruCache::new(X + 1)
+            )
+            .is_ok());
+        let mut b = a.clone();
+        b.version = Version::V2;
+        assert_eq!(
+            b.verify_with_context::<Sha512>(b"zudo", DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn ring_hash() {
+        let rng = &mut OsRng;
+        let a = CLSAG::sign::<Xof<Shake256>>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        let b = CLSAG::sign::<Wide<Keccak256>>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        assert!(a.verify::<Xof<Shake256>>(DATA_0).is_ok());
+        assert!(b.verify::<Wide<Keccak256>>(DATA_0).is_ok());
+        assert_eq!(
+            a.verify::<Wide<Keccak256>>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+    }
+    #[test]
+    fn tag() {
+        let rng = &mut OsRng;
+        let a =
+            CLSAG::sign_with_tag::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), b"2024", b"", DATA_0)
+                .unwrap();
+        let b =
+            CLSAG::sign_with_tag::<Sha512>(rng, &SECRETS_0, RINGS_1.clone(), b"2024", b"", DATA_1)
+                .unwrap();
+        let c =
+            CLSAG::sign_with_tag::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), b"2028", b"", DATA_0)
+                .unwrap();
+        let d = CLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        assert!(a.verify_with_tag::<Sha512>(b"2024", b"", DATA_0).is_ok());
+        assert!(b.verify_with_tag::<Sha512>(b"2024", b"", DATA_1).is_ok());
+        assert!(c.verify_with_tag::<Sha512>(b"2028", b"", DATA_0).is_ok());
+        assert_eq!(
+            a.verify_with_tag::<Sha512>(b"2028", b"", DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+        assert_eq!(a.images[0], SECRETS_0[0].image_with_tag::<Sha512>(b"2024"));
+        assert_ne!(a.images[0], c.images[0]);
+        assert_ne!(a.images[0], d.images[0]);
+        assert_eq!(
+            CLSAG::link_with_tags::<Sha512>(
+                &[(&a, b"2024", DATA_0), (&b, b"2024", DATA_1.as_bytes())],
+                b""
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            CLSAG::link_with_tags::<Sha512>(&[(&a, b"2024", DATA_0), (&c, b"2028", DATA_0)], b""),
+            Err(Error::TagMismatch)
+        );
+        assert_eq!(
+            CLSAG::link_with_tags::<Sha512>(&[(&a, b"2024", DATA_0), (&a, b"2028", DATA_0)], b""),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(
+            CLSAG::<Ristretto255>::link_with_tags::<Sha512>(&[], b""),
+            Ok(false)
+        );
+        let e = CLSAG::sign_with_tag_and_version::<Sha512>(
+            rng,
+            &SECRETS_0,
+            RINGS_0.clone(),
+            b"2024",
+            Version::V3,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(e.verify_with_tag::<Sha512>(b"2024", b"", DATA_0).is_ok());
+        assert_eq!(
+            e.images[0],
+            SECRETS_0[0].image_with_tag_and_version::<Sha512>(b"2024", Version::V3)
+        );
+        assert_eq!(
+            CLSAG::link_with_tags::<Sha512>(&[(&a, b"2024", DATA_0), (&e, b"2024", DATA_0)], b""),
+            Ok(false)
+        );
+    }
+    fn group<G: Group>() {
+        let rng = &mut OsRng;
+        let secrets = (0..Y)
+            .map(|_| SecretKey::<G>::random(rng))
+            .collect::<Vec<_>>();
+        let mut rings = (0..X)
+            .map(|_| {
+                (0..Y)
+                    .map(|_| SecretKey::<G>::random(rng).public_key())
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+        rings.push(secrets.iter().map(SecretKey::public_key).collect());
+        let a = CLSAG::sign_with_index::<Sha512>(rng, &secrets, rings, X, Version::V2, b"", DATA_0)
+            .unwrap();
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(a.verify::<Sha512>(DATA_1), Err(Error::ChallengeMismatch));
+        let bytes = bincode::serialize(&a).unwrap();
+        assert_eq!(bincode::deserialize::<CLSAG<G>>(&bytes).unwrap(), a);
+    }
+    #[test]
+    fn groups() {
+        group::<Ristretto255>();
+        group::<crate::Ed25519>();
+        #[cfg(feature = "secp256k1")]
+        group::<crate::Secp256k1>();
+        #[cfg(feature = "jubjub")]
+        group::<crate::Jubjub>();
+    }
 }
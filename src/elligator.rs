@@ -0,0 +1,170 @@
+use crypto_bigint::impl_modulus;
+use crypto_bigint::modular::constant_mod::Residue;
+use crypto_bigint::subtle::Choice;
+use crypto_bigint::subtle::ConditionallyNegatable;
+use crypto_bigint::subtle::ConditionallySelectable;
+use crypto_bigint::subtle::ConstantTimeEq;
+use crypto_bigint::Encoding;
+use crypto_bigint::U256;
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::edwards::EdwardsPoint;
+#[cfg(feature = "jubjub")]
+use group::ff::Field;
+impl_modulus!(
+    Curve25519Modulus,
+    U256,
+    "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed"
+);
+type FieldElement = Residue<Curve25519Modulus, { U256::LIMBS }>;
+const J: FieldElement = FieldElement::new(&U256::from_u64(486662));
+const TWO_POW_256: FieldElement = FieldElement::new(&U256::from_u64(38));
+const SQRT_M1: FieldElement = FieldElement::new(&U256::from_be_hex(
+    "2b8324804fc1df0b2b4d00993dfbd7a72f431806ad2fe478c4ee1b274a0ea0b0",
+));
+const TWO_POW_C1: FieldElement = FieldElement::new(&U256::from_be_hex(
+    "2b8324804fc1df0b2b4d00993dfbd7a72f431806ad2fe478c4ee1b274a0ea0b1",
+));
+const SQRT_M486664: FieldElement = FieldElement::new(&U256::from_be_hex(
+    "0f26edf460a006bbd27b08dc03fc4f7ec5a1d3d14b7d1a82cc6e04aaff457e06",
+));
+const C4: U256 =
+    U256::from_be_hex("0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd");
+pub(crate) fn map_to_edwards25519(uniform: &[u8]) -> EdwardsPoint {
+    let (x_mn, x_md, y_mn) = map_to_curve25519(field_from_uniform(uniform));
+    let mut x_n = x_mn * SQRT_M486664;
+    let mut x_d = x_md * y_mn;
+    let mut y_n = x_mn - x_md;
+    let mut y_d = x_mn + x_md;
+    let exceptional = (x_d * y_d).ct_eq(&FieldElement::ZERO);
+    x_n.conditional_assign(&FieldElement::ZERO, exceptional);
+    x_d.conditional_assign(&FieldElement::ONE, exceptional);
+    y_n.conditional_assign(&FieldElement::ONE, exceptional);
+    y_d.conditional_assign(&FieldElement::ONE, exceptional);
+    let x = x_n * x_d.invert().0;
+    let y = y_n * y_d.invert().0;
+    let mut bytes = y.retrieve().to_le_bytes();
+    bytes[31] |= sgn0(&x).unwrap_u8() << 7;
+    CompressedEdwardsY(bytes).decompress().unwrap()
+}
+fn map_to_curve25519(u: FieldElement) -> (FieldElement, FieldElement, FieldElement) {
+    let tv1 = u.square();
+    let tv1 = tv1 + tv1;
+    let x_d = tv1 + FieldElement::ONE;
+    let x1_n = -J;
+    let tv2 = x_d.square();
+    let gx_d = tv2 * x_d;
+    let gx1 = (J * tv1 * x1_n + tv2) * x1_n;
+    let tv3 = gx_d.square();
+    let tv2 = tv3.square();
+    let tv3 = tv3 * gx_d * gx1;
+    let y11 = (tv2 * tv3).pow(&C4) * tv3;
+    let y12 = y11 * SQRT_M1;
+    let e1 = (y11.square() * gx_d).ct_eq(&gx1);
+    let y1 = FieldElement::conditional_select(&y12, &y11, e1);
+    let x2_n = x1_n * tv1;
+    let y21 = y11 * u * TWO_POW_C1;
+    let y22 = y21 * SQRT_M1;
+    let gx2 = gx1 * tv1;
+    let e2 = (y21.square() * gx_d).ct_eq(&gx2);
+    let y2 = FieldElement::conditional_select(&y22, &y21, e2);
+    let e3 = (y1.square() * gx_d).ct_eq(&gx1);
+    let x_n = FieldElement::conditional_select(&x2_n, &x1_n, e3);
+    let mut y = FieldElement::conditional_select(&y2, &y1, e3);
+    let e4 = sgn0(&y);
+    y.conditional_negate(e3 ^ e4);
+    (x_n, x_d, y)
+}
+fn field_from_uniform(uniform: &[u8]) -> FieldElement {
+    let mut high = [0; 32];
+    high[16..].copy_from_slice(&uniform[..16]);
+    FieldElement::new(&U256::from_be_slice(&high)) * TWO_POW_256
+        + FieldElement::new(&U256::from_be_slice(&uniform[16..]))
+}
+fn sgn0(element: &FieldElement) -> Choice {
+    Choice::from(element.retrieve().to_le_bytes()[0] & 1)
+}
+#[cfg(feature = "jubjub")]
+pub(crate) fn map_to_jubjub(u: jubjub::Fq) -> jubjub::ExtendedPoint {
+    let z = jubjub::Fq::from(5);
+    let k = -jubjub::Fq::from(40964);
+    let j_over_k = jubjub::Fq::from(40962) * k.invert().unwrap();
+    let k_squared_inverse = k.square().invert().unwrap();
+    let g = |x: jubjub::Fq| (x.square() + j_over_k * x + k_squared_inverse) * x;
+    let x1 = -j_over_k
+        * (jubjub::Fq::one() + z * u.square())
+            .invert()
+            .unwrap_or(jubjub::Fq::zero());
+    let x1 = jubjub::Fq::conditional_select(&x1, &-j_over_k, x1.ct_eq(&jubjub::Fq::zero()));
+    let x2 = -x1 - j_over_k;
+    let y1 = g(x1).sqrt();
+    let y2 = g(x2).sqrt();
+    let e = y1.is_some();
+    let x = jubjub::Fq::conditional_select(&x2, &x1, e);
+    let mut y = jubjub::Fq::conditional_select(
+        &y2.unwrap_or(jubjub::Fq::zero()),
+        &y1.unwrap_or(jubjub::Fq::zero()),
+        e,
+    );
+    y.conditional_negate(Choice::from(y.to_bytes()[0] & 1) ^ e);
+    let s = x * k;
+    let t = y * k;
+    let exceptional =
+        t.ct_eq(&jubjub::Fq::zero()) | (s + jubjub::Fq::one()).ct_eq(&jubjub::Fq::zero());
+    let u = s * t.invert().unwrap_or(jubjub::Fq::zero());
+    let v = (s - jubjub::Fq::one())
+        * (s + jubjub::Fq::one())
+            .invert()
+            .unwrap_or(jubjub::Fq::zero());
+    jubjub::AffinePoint::from_raw_unchecked(
+        jubjub::Fq::conditional_select(&u, &jubjub::Fq::zero(), exceptional),
+        jubjub::Fq::conditional_select(&v, &jubjub::Fq::one(), exceptional),
+    )
+    .into()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn field_from_uniform_reduces() {
+        for (uniform, element) in [
+            (
+                [0xff; 48],
+                "00000000000000000000000000000025ffffffffffffffffffffffffffffffff",
+            ),
+            (
+                [0x01; 48],
+                "0101010101010101010101010101010127272727272727272727272727272727",
+            ),
+        ] {
+            assert_eq!(
+                field_from_uniform(&uniform).retrieve(),
+                U256::from_be_hex(element)
+            );
+        }
+    }
+    #[cfg(feature = "jubjub")]
+    #[test]
+    fn map_to_jubjub_elligator2() {
+        for (u, point_u, point_v) in [
+            (
+                jubjub::Fq::zero(),
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "0100000000000000000000000000000000000000000000000000000000000000",
+            ),
+            (
+                jubjub::Fq::one(),
+                "e30024dad66ca05a3f73401417f2c2c51e39fb30fddcf7828b557a76c0a94f69",
+                "9d4bd08b3912d9ea122d7a38b02788ba9520e4593f6ace5294102118026e113b",
+            ),
+            (
+                jubjub::Fq::from(7),
+                "b98fa606af3538064714981a7083006b79f83b5a340dc0075c7e69eb05f4ac3a",
+                "a7f27067a8bee1501362496313f1b44fd5227dfb83331868c46f83dc3c635d57",
+            ),
+        ] {
+            let point = jubjub::AffinePoint::from(map_to_jubjub(u));
+            assert_eq!(hex::encode(point.get_u().to_bytes()), point_u);
+            assert_eq!(hex::encode(point.get_v().to_bytes()), point_v);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    EmptyRing,
+    EmptySecrets,
+    RingWidthMismatch,
+    ResponseCountMismatch,
+    ImageCountMismatch,
+    NonCanonicalChallenge,
+    NonCanonicalScalar { index: usize },
+    InvalidPoint { row: usize, col: usize },
+    InvalidSecretKey,
+    InvalidPublicKey,
+    InvalidKeyImage { index: usize },
+    ChallengeMismatch,
+    UnsupportedContext,
+    UnsupportedVersion,
+    SignerIndexOutOfRange,
+    SignerKeyMismatch,
+    IdentityMember { row: usize, col: usize },
+    DuplicateMember { row: usize },
+    NonCanonicalRing,
+    InvalidExpandLength,
+    InvalidProofPoint,
+    InvalidProof,
+    DuplicateImage,
+    ThresholdMismatch,
+    TagMismatch,
+    Unbalanced,
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EmptyRing => write!(f, "ring is empty"),
+            Error::EmptySecrets => write!(f, "no secrets given"),
+            Error::RingWidthMismatch => write!(f, "ring members differ in width"),
+            Error::ResponseCountMismatch => write!(f, "response count does not match ring"),
+            Error::ImageCountMismatch => write!(f, "image count does not match ring width"),
+            Error::NonCanonicalChallenge => write!(f, "challenge is not a canonical scalar"),
+            Error::NonCanonicalScalar { index } => {
+                write!(f, "response {} is not a canonical scalar", index)
+            }
+            Error::InvalidPoint { row, col } => {
+                write!(f, "ring member ({}, {}) is not a valid point", row, col)
+            }
+            Error::InvalidSecretKey => write!(f, "secret key is not a canonical scalar"),
+            Error::InvalidPublicKey => write!(f, "public key is not a valid point"),
+            Error::InvalidKeyImage { index } => {
+                write!(f, "key image {} is not a valid point", index)
+            }
+            Error::ChallengeMismatch => write!(f, "challenge does not match"),
+            Error::UnsupportedContext => write!(f, "version does not support a context"),
+            Error::UnsupportedVersion => write!(f, "version is only accepted by verify_legacy"),
+            Error::SignerIndexOutOfRange => write!(f, "signer index is outside the ring"),
+            Error::SignerKeyMismatch => write!(f, "ring member at signer index is not the signer"),
+            Error::IdentityMember { row, col } => {
+                write!(f, "ring member ({}, {}) is the identity", row, col)
+            }
+            Error::DuplicateMember { row } => write!(f, "ring member {} is a duplicate", row),
+            Error::NonCanonicalRing => write!(f, "ring is not in canonical order"),
+            Error::InvalidExpandLength => write!(f, "requested expansion length is too long"),
+            Error::InvalidProofPoint => write!(f, "proof element is not a valid point"),
+            Error::InvalidProof => write!(f, "proof does not verify"),
+            Error::DuplicateImage => write!(f, "key images are not distinct"),
+            Error::ThresholdMismatch => write!(f, "signature threshold does not match"),
+            Error::TagMismatch => write!(f, "signatures were made under different tags"),
+            Error::Unbalanced => write!(f, "input and output amounts differ"),
+        }
+    }
+}
+impl std::error::Error for Error {}
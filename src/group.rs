@@ -0,0 +1,478 @@
+#[cfg(feature = "jubjub")]
+use crate::elligator;
+use crate::hash_to_curve;
+use crate::point_hash_bytes;
+use crate::scalar_from_canonical;
+use crate::scalar_from_hash;
+use crate::scalar_random;
+use crate::RingHash;
+use crate::Version;
+#[cfg(feature = "jubjub")]
+use crate::Zeroizing;
+use crate::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::Identity;
+use curve25519_dalek::traits::MultiscalarMul;
+use curve25519_dalek::traits::VartimeMultiscalarMul;
+use curve25519_dalek::Scalar;
+use rand_core::CryptoRngCore;
+use sha2::Sha512;
+use std::fmt;
+use std::hash;
+use std::ops::Add;
+use std::ops::Mul;
+use std::ops::Neg;
+use std::ops::Sub;
+#[cfg(feature = "zeroize")]
+pub use zeroize::Zeroize;
+#[cfg(not(feature = "zeroize"))]
+pub trait Zeroize {}
+#[cfg(not(feature = "zeroize"))]
+impl<T> Zeroize for T {}
+pub trait Group:
+    Clone + Copy + fmt::Debug + Default + Eq + PartialEq + Send + Sync + 'static
+{
+    type Point: Copy
+        + fmt::Debug
+        + Eq
+        + Send
+        + Sync
+        + Add<Output = Self::Point>
+        + Sub<Output = Self::Point>
+        + Mul<Self::Scalar, Output = Self::Point>;
+    type Scalar: Copy
+        + fmt::Debug
+        + Eq
+        + Send
+        + Sync
+        + Zeroize
+        + Add<Output = Self::Scalar>
+        + Sub<Output = Self::Scalar>
+        + Mul<Output = Self::Scalar>
+        + Neg<Output = Self::Scalar>;
+    type Encoding: AsRef<[u8]>
+        + for<'a> TryFrom<&'a [u8]>
+        + Copy
+        + fmt::Debug
+        + Eq
+        + Ord
+        + hash::Hash
+        + Send
+        + Sync;
+    fn generator() -> Self::Point;
+    fn identity() -> Self::Point;
+    fn scalar_random(rng: &mut impl CryptoRngCore) -> Self::Scalar;
+    fn scalar_from_u64(value: u64) -> Self::Scalar;
+    fn scalar_from_canonical(bytes: [u8; 32]) -> Option<Self::Scalar>;
+    fn scalar_to_bytes(scalar: &Self::Scalar) -> [u8; 32];
+    fn scalar_from_hash<Hash: RingHash>(hash: Hash) -> Self::Scalar;
+    fn scalar_from_standard(bytes: &[u8]) -> Self::Scalar;
+    fn point_from_bytes(bytes: &Self::Encoding) -> Option<Self::Point>;
+    fn point_to_bytes(point: &Self::Point) -> Self::Encoding;
+    fn hash_to_point<Hash: RingHash>(version: Version, bytes: &[u8]) -> Self::Point;
+    fn coordinates(_: &Self::Encoding) -> Option<[[u8; 32]; 2]> {
+        None
+    }
+    fn multiscalar_mul(scalars: &[Self::Scalar], points: &[Self::Point]) -> Self::Point {
+        scalars
+            .iter()
+            .zip(points)
+            .fold(Self::identity(), |sum, (scalar, point)| {
+                sum + *point * *scalar
+            })
+    }
+    fn vartime_multiscalar_mul(scalars: &[Self::Scalar], points: &[Self::Point]) -> Self::Point {
+        Self::multiscalar_mul(scalars, points)
+    }
+}
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ristretto255;
+impl Group for Ristretto255 {
+    type Point = RistrettoPoint;
+    type Scalar = Scalar;
+    type Encoding = [u8; 32];
+    fn generator() -> RistrettoPoint {
+        RISTRETTO_BASEPOINT_POINT
+    }
+    fn identity() -> RistrettoPoint {
+        RistrettoPoint::identity()
+    }
+    fn scalar_random(rng: &mut impl CryptoRngCore) -> Scalar {
+        scalar_random(rng)
+    }
+    fn scalar_from_u64(value: u64) -> Scalar {
+        Scalar::from(value)
+    }
+    fn scalar_from_canonical(bytes: [u8; 32]) -> Option<Scalar> {
+        scalar_from_canonical(bytes)
+    }
+    fn scalar_to_bytes(scalar: &Scalar) -> [u8; 32] {
+        scalar.to_bytes()
+    }
+    fn scalar_from_hash<Hash: RingHash>(hash: Hash) -> Scalar {
+        scalar_from_hash(hash)
+    }
+    fn scalar_from_standard(bytes: &[u8]) -> Scalar {
+        hash_to_curve::hash_to_scalar::<Sha512>(bytes, hash_to_curve::DST_SCALAR)
+    }
+    fn point_from_bytes(bytes: &[u8; 32]) -> Option<RistrettoPoint> {
+        CompressedRistretto(*bytes).decompress()
+    }
+    fn point_to_bytes(point: &RistrettoPoint) -> [u8; 32] {
+        point.compress().to_bytes()
+    }
+    fn hash_to_point<Hash: RingHash>(version: Version, bytes: &[u8]) -> RistrettoPoint {
+        point_hash_bytes::<Hash>(version, bytes)
+    }
+    fn multiscalar_mul(scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+        RistrettoPoint::multiscalar_mul(scalars, points)
+    }
+    fn vartime_multiscalar_mul(scalars: &[Scalar], points: &[RistrettoPoint]) -> RistrettoPoint {
+        RistrettoPoint::vartime_multiscalar_mul(scalars, points)
+    }
+}
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ed25519;
+impl Ed25519 {
+    const DST_POINT: &'static [u8] = b"ring-signature-V3-edwards25519_XMD:SHA-512_ELL2_RO_";
+    const DST_SCALAR: &'static [u8] = b"ring-signature-V3-edwards25519_XMD:SHA-512_H2S_";
+}
+impl Group for Ed25519 {
+    type Point = EdwardsPoint;
+    type Scalar = Scalar;
+    type Encoding = [u8; 32];
+    fn generator() -> EdwardsPoint {
+        ED25519_BASEPOINT_POINT
+    }
+    fn identity() -> EdwardsPoint {
+        EdwardsPoint::identity()
+    }
+    fn scalar_random(rng: &mut impl CryptoRngCore) -> Scalar {
+        scalar_random(rng)
+    }
+    fn scalar_from_u64(value: u64) -> Scalar {
+        Scalar::from(value)
+    }
+    fn scalar_from_canonical(bytes: [u8; 32]) -> Option<Scalar> {
+        scalar_from_canonical(bytes)
+    }
+    fn scalar_to_bytes(scalar: &Scalar) -> [u8; 32] {
+        scalar.to_bytes()
+    }
+    fn scalar_from_hash<Hash: RingHash>(hash: Hash) -> Scalar {
+        scalar_from_hash(hash)
+    }
+    fn scalar_from_standard(bytes: &[u8]) -> Scalar {
+        hash_to_curve::hash_to_scalar::<Sha512>(bytes, Ed25519::DST_SCALAR)
+    }
+    fn point_from_bytes(bytes: &[u8; 32]) -> Option<EdwardsPoint> {
+        CompressedEdwardsY(*bytes)
+            .decompress()
+            .filter(|point| point.is_torsion_free() && point.compress().as_bytes() == bytes)
+    }
+    fn point_to_bytes(point: &EdwardsPoint) -> [u8; 32] {
+        point.compress().to_bytes()
+    }
+    fn hash_to_point<Hash: RingHash>(_: Version, bytes: &[u8]) -> EdwardsPoint {
+        hash_to_curve::hash_to_edwards25519(bytes, Ed25519::DST_POINT)
+    }
+    fn multiscalar_mul(scalars: &[Scalar], points: &[EdwardsPoint]) -> EdwardsPoint {
+        EdwardsPoint::multiscalar_mul(scalars, points)
+    }
+    fn vartime_multiscalar_mul(scalars: &[Scalar], points: &[EdwardsPoint]) -> EdwardsPoint {
+        EdwardsPoint::vartime_multiscalar_mul(scalars, points)
+    }
+}
+#[cfg(feature = "secp256k1")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Secp256k1;
+#[cfg(feature = "secp256k1")]
+impl Secp256k1 {
+    const DST_POINT: &'static [u8] = b"ring-signature-V3-secp256k1_XMD:SHA-256_SSWU_RO_";
+    const DST_SCALAR: &'static [u8] = b"ring-signature-V3-secp256k1_XMD:SHA-256_H2S_";
+    fn scalar_from_wide(bytes: &[u8; 64]) -> k256::Scalar {
+        <k256::Scalar as k256::elliptic_curve::ops::Reduce<k256::elliptic_curve::bigint::U512>>::reduce_bytes(
+            bytes.into(),
+        )
+    }
+}
+#[cfg(feature = "secp256k1")]
+impl Group for Secp256k1 {
+    type Point = k256::ProjectivePoint;
+    type Scalar = k256::Scalar;
+    type Encoding = [u8; 33];
+    fn generator() -> k256::ProjectivePoint {
+        k256::ProjectivePoint::GENERATOR
+    }
+    fn identity() -> k256::ProjectivePoint {
+        k256::ProjectivePoint::IDENTITY
+    }
+    fn scalar_random(rng: &mut impl CryptoRngCore) -> k256::Scalar {
+        <k256::Scalar as k256::elliptic_curve::Field>::random(rng)
+    }
+    fn scalar_from_u64(value: u64) -> k256::Scalar {
+        k256::Scalar::from(value)
+    }
+    fn scalar_from_canonical(bytes: [u8; 32]) -> Option<k256::Scalar> {
+        <k256::Scalar as k256::elliptic_curve::PrimeField>::from_repr(bytes.into()).into()
+    }
+    fn scalar_to_bytes(scalar: &k256::Scalar) -> [u8; 32] {
+        scalar.to_bytes().into()
+    }
+    fn scalar_from_hash<Hash: RingHash>(hash: Hash) -> k256::Scalar {
+        Secp256k1::scalar_from_wide(&hash.finalize_wide())
+    }
+    fn scalar_from_standard(bytes: &[u8]) -> k256::Scalar {
+        let mut wide = [0; 64];
+        wide[16..].copy_from_slice(
+            &hash_to_curve::expand_message_xmd::<sha2::Sha256>(bytes, Secp256k1::DST_SCALAR, 48)
+                .unwrap(),
+        );
+        Secp256k1::scalar_from_wide(&wide)
+    }
+    fn point_from_bytes(bytes: &[u8; 33]) -> Option<k256::ProjectivePoint> {
+        <k256::ProjectivePoint as k256::elliptic_curve::group::GroupEncoding>::from_bytes(
+            bytes.into(),
+        )
+        .into()
+    }
+    fn point_to_bytes(point: &k256::ProjectivePoint) -> [u8; 33] {
+        <k256::ProjectivePoint as k256::elliptic_curve::group::GroupEncoding>::to_bytes(point)
+            .into()
+    }
+    fn hash_to_point<Hash: RingHash>(_: Version, bytes: &[u8]) -> k256::ProjectivePoint {
+        hash_to_curve::hash_to_secp256k1(bytes, Secp256k1::DST_POINT)
+    }
+}
+#[cfg(feature = "jubjub")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Jubjub;
+#[cfg(feature = "jubjub")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct JubjubScalar(pub jubjub::Fr);
+#[cfg(feature = "jubjub")]
+impl Add for JubjubScalar {
+    type Output = JubjubScalar;
+    fn add(self, other: JubjubScalar) -> JubjubScalar {
+        JubjubScalar(self.0 + other.0)
+    }
+}
+#[cfg(feature = "jubjub")]
+impl Sub for JubjubScalar {
+    type Output = JubjubScalar;
+    fn sub(self, other: JubjubScalar) -> JubjubScalar {
+        JubjubScalar(self.0 - other.0)
+    }
+}
+#[cfg(feature = "jubjub")]
+impl Mul for JubjubScalar {
+    type Output = JubjubScalar;
+    fn mul(self, other: JubjubScalar) -> JubjubScalar {
+        JubjubScalar(self.0 * other.0)
+    }
+}
+#[cfg(feature = "jubjub")]
+impl Neg for JubjubScalar {
+    type Output = JubjubScalar;
+    fn neg(self) -> JubjubScalar {
+        JubjubScalar(-self.0)
+    }
+}
+#[cfg(feature = "jubjub")]
+impl Mul<JubjubScalar> for jubjub::SubgroupPoint {
+    type Output = jubjub::SubgroupPoint;
+    fn mul(self, scalar: JubjubScalar) -> jubjub::SubgroupPoint {
+        self * scalar.0
+    }
+}
+#[cfg(all(feature = "jubjub", feature = "zeroize"))]
+impl zeroize::DefaultIsZeroes for JubjubScalar {}
+#[cfg(feature = "jubjub")]
+impl Jubjub {
+    const DST_POINT: &'static [u8] = b"ring-signature-V3-jubjub-XMD:SHA-512-elligator2";
+    const DST_SCALAR: &'static [u8] = b"ring-signature-V3-jubjub-XMD:SHA-512-hash-to-scalar";
+}
+#[cfg(feature = "jubjub")]
+impl Group for Jubjub {
+    type Point = jubjub::SubgroupPoint;
+    type Scalar = JubjubScalar;
+    type Encoding = [u8; 32];
+    fn generator() -> jubjub::SubgroupPoint {
+        <jubjub::SubgroupPoint as group::Group>::generator()
+    }
+    fn identity() -> jubjub::SubgroupPoint {
+        <jubjub::SubgroupPoint as group::Group>::identity()
+    }
+    fn scalar_random(rng: &mut impl CryptoRngCore) -> JubjubScalar {
+        let mut bytes = Zeroizing::new([0u8; 64]);
+        rng.fill_bytes(&mut *bytes);
+        JubjubScalar(jubjub::Fr::from_bytes_wide(&bytes))
+    }
+    fn scalar_from_u64(value: u64) -> JubjubScalar {
+        JubjubScalar(jubjub::Fr::from(value))
+    }
+    fn scalar_from_canonical(bytes: [u8; 32]) -> Option<JubjubScalar> {
+        Option::from(jubjub::Fr::from_bytes(&bytes)).map(JubjubScalar)
+    }
+    fn scalar_to_bytes(scalar: &JubjubScalar) -> [u8; 32] {
+        scalar.0.to_bytes()
+    }
+    fn scalar_from_hash<Hash: RingHash>(hash: Hash) -> JubjubScalar {
+        JubjubScalar(jubjub::Fr::from_bytes_wide(&hash.finalize_wide()))
+    }
+    fn scalar_from_standard(bytes: &[u8]) -> JubjubScalar {
+        let uniform = hash_to_curve::expand_message_xmd::<Sha512>(bytes, Jubjub::DST_SCALAR, 64);
+        JubjubScalar(jubjub::Fr::from_bytes_wide(
+            &uniform.unwrap().try_into().unwrap(),
+        ))
+    }
+    fn point_from_bytes(bytes: &[u8; 32]) -> Option<jubjub::SubgroupPoint> {
+        Option::from(<jubjub::SubgroupPoint as group::GroupEncoding>::from_bytes(
+            bytes,
+        ))
+        .filter(|point| Jubjub::point_to_bytes(point) == *bytes)
+    }
+    fn point_to_bytes(point: &jubjub::SubgroupPoint) -> [u8; 32] {
+        <jubjub::SubgroupPoint as group::GroupEncoding>::to_bytes(point)
+    }
+    fn hash_to_point<Hash: RingHash>(version: Version, bytes: &[u8]) -> jubjub::SubgroupPoint {
+        let uniform = match version {
+            Version::V3 => {
+                hash_to_curve::expand_message_xmd::<Sha512>(bytes, Jubjub::DST_POINT, 128).unwrap()
+            }
+            _ => {
+                let mut hash = Hash::init();
+                match <[u8; 32]>::try_from(bytes)
+                    .ok()
+                    .as_ref()
+                    .and_then(Jubjub::coordinates)
+                {
+                    Some(coordinates) => hash.absorb_elements(&coordinates),
+                    None => hash.absorb(bytes),
+                }
+                [
+                    hash.clone().chain_absorb([0]).finalize_wide(),
+                    hash.chain_absorb([1]).finalize_wide(),
+                ]
+                .concat()
+            }
+        };
+        let point = uniform
+            .chunks(64)
+            .map(|wide| {
+                elligator::map_to_jubjub(jubjub::Fq::from_bytes_wide(wide.try_into().unwrap()))
+            })
+            .fold(jubjub::ExtendedPoint::identity(), |sum, point| sum + point);
+        group::cofactor::CofactorGroup::clear_cofactor(&point)
+    }
+    fn coordinates(encoding: &[u8; 32]) -> Option<[[u8; 32]; 2]> {
+        Option::<jubjub::AffinePoint>::from(jubjub::AffinePoint::from_bytes(*encoding))
+            .map(|point| [point.get_u().to_bytes(), point.get_v().to_bytes()])
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    fn group<G: Group>() {
+        let a = G::scalar_random(&mut OsRng);
+        let b = G::scalar_random(&mut OsRng);
+        assert_eq!(G::scalar_from_canonical(G::scalar_to_bytes(&a)), Some(a));
+        assert_eq!(G::scalar_from_canonical([0xff; 32]), None);
+        let point = G::generator() * a;
+        assert_eq!(G::point_from_bytes(&G::point_to_bytes(&point)), Some(point));
+        assert_eq!(
+            G::multiscalar_mul(&[a, b], &[G::generator(), point]),
+            G::generator() * (a + b * a)
+        );
+        assert_eq!(
+            G::vartime_multiscalar_mul(&[a, b], &[G::generator(), point]),
+            G::generator() * (a + b * a)
+        );
+        assert_eq!(
+            G::generator() * G::scalar_from_u64(2),
+            G::generator() + G::generator()
+        );
+        for version in [Version::V2, Version::V3] {
+            let h = G::hash_to_point::<Sha512>(version, b"zudo");
+            assert_ne!(h, G::identity());
+            assert_eq!(h, G::hash_to_point::<Sha512>(version, b"zudo"));
+            assert_ne!(h, G::hash_to_point::<Sha512>(version, b"odu"));
+        }
+    }
+    #[test]
+    fn ristretto255() {
+        group::<Ristretto255>();
+        assert_ne!(
+            Ristretto255::hash_to_point::<Sha512>(Version::V2, b"zudo"),
+            Ristretto255::hash_to_point::<Sha512>(Version::V3, b"zudo")
+        );
+    }
+    #[test]
+    fn ed25519() {
+        group::<Ed25519>();
+        let torsion = curve25519_dalek::constants::EIGHT_TORSION[1];
+        let point = ED25519_BASEPOINT_POINT + torsion;
+        assert_eq!(
+            Ed25519::point_from_bytes(&point.compress().to_bytes()),
+            None
+        );
+        assert!(Ed25519::hash_to_point::<Sha512>(Version::V2, b"zudo").is_torsion_free());
+        assert_eq!(
+            Ed25519::hash_to_point::<Sha512>(Version::V2, b"zudo"),
+            hash_to_curve::hash_to_edwards25519(b"zudo", Ed25519::DST_POINT)
+        );
+        assert_eq!(
+            Ed25519::hash_to_point::<Sha512>(Version::Legacy, b"zudo"),
+            Ed25519::hash_to_point::<Sha512>(Version::V3, b"zudo")
+        );
+    }
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn secp256k1() {
+        group::<Secp256k1>();
+        assert_eq!(Secp256k1::point_to_bytes(&Secp256k1::identity()), [0; 33]);
+        assert_eq!(
+            Secp256k1::hash_to_point::<Sha512>(Version::V2, b"zudo"),
+            hash_to_curve::hash_to_secp256k1(b"zudo", Secp256k1::DST_POINT)
+        );
+        assert_eq!(
+            Secp256k1::hash_to_point::<Sha512>(Version::Legacy, b"zudo"),
+            Secp256k1::hash_to_point::<Sha512>(Version::V3, b"zudo")
+        );
+    }
+    #[cfg(feature = "jubjub")]
+    #[test]
+    fn jubjub() {
+        group::<Jubjub>();
+        let torsion = jubjub::ExtendedPoint::from(jubjub::AffinePoint::from_raw_unchecked(
+            jubjub::Fq::zero(),
+            -jubjub::Fq::one(),
+        ));
+        let point = jubjub::ExtendedPoint::from(Jubjub::generator()) + torsion;
+        assert_eq!(
+            Jubjub::point_from_bytes(&jubjub::AffinePoint::from(point).to_bytes()),
+            None
+        );
+        assert_eq!(
+            Jubjub::hash_to_point::<crate::Poseidon>(Version::V2, b"zudo"),
+            Jubjub::hash_to_point::<crate::Poseidon>(Version::V2, b"zudo")
+        );
+        assert_ne!(
+            Jubjub::hash_to_point::<Sha512>(Version::V2, b"zudo"),
+            Jubjub::hash_to_point::<Sha512>(Version::V3, b"zudo")
+        );
+        let generator = jubjub::AffinePoint::from(jubjub::ExtendedPoint::from(Jubjub::generator()));
+        assert_eq!(
+            Jubjub::coordinates(&Jubjub::point_to_bytes(&Jubjub::generator())),
+            Some([generator.get_u().to_bytes(), generator.get_v().to_bytes()])
+        );
+        assert_eq!(Jubjub::coordinates(&[0xff; 32]), None);
+        assert_eq!(Ristretto255::coordinates(&[0; 32]), None);
+    }
+}
@@ -0,0 +1,242 @@
+#[cfg(feature = "jubjub")]
+use digest::typenum::U2;
+use digest::typenum::U32;
+use digest::typenum::U64;
+use digest::Digest;
+use digest::ExtendableOutput;
+use digest::Update;
+use digest::XofReader;
+#[cfg(feature = "jubjub")]
+use neptune::poseidon::PoseidonConstants;
+#[cfg(feature = "jubjub")]
+use std::sync::OnceLock;
+pub trait RingHash: Clone {
+    fn init() -> Self;
+    fn absorb(&mut self, data: &[u8]);
+    fn finalize_wide(self) -> [u8; 64];
+    fn absorb_elements(&mut self, elements: &[[u8; 32]]) {
+        for element in elements {
+            self.absorb(element);
+        }
+    }
+    fn chain_absorb(mut self, data: impl AsRef<[u8]>) -> Self {
+        self.absorb(data.as_ref());
+        self
+    }
+}
+impl<Hash: Digest<OutputSize = U64> + Clone> RingHash for Hash {
+    fn init() -> Hash {
+        Hash::new()
+    }
+    fn absorb(&mut self, data: &[u8]) {
+        Digest::update(self, data);
+    }
+    fn finalize_wide(self) -> [u8; 64] {
+        self.finalize().into()
+    }
+}
+#[derive(Clone, Debug, Default)]
+pub struct Xof<Hash>(Hash);
+impl<Hash: ExtendableOutput + Update + Default + Clone> RingHash for Xof<Hash> {
+    fn init() -> Xof<Hash> {
+        Xof(Hash::default())
+    }
+    fn absorb(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+    fn finalize_wide(self) -> [u8; 64] {
+        let mut bytes = [0; 64];
+        self.0.finalize_xof().read(&mut bytes);
+        bytes
+    }
+}
+#[derive(Clone, Debug, Default)]
+pub struct Wide<Hash>(Hash);
+impl<Hash: Digest<OutputSize = U32> + Clone> RingHash for Wide<Hash> {
+    fn init() -> Wide<Hash> {
+        Wide(Hash::new())
+    }
+    fn absorb(&mut self, data: &[u8]) {
+        Digest::update(&mut self.0, data);
+    }
+    fn finalize_wide(self) -> [u8; 64] {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(&self.0.clone().chain_update([0]).finalize());
+        bytes[32..].copy_from_slice(&self.0.chain_update([1]).finalize());
+        bytes
+    }
+}
+#[cfg(feature = "jubjub")]
+#[derive(Clone, Debug)]
+pub struct Poseidon {
+    state: jubjub::Fq,
+    buffer: Vec<u8>,
+}
+#[cfg(feature = "jubjub")]
+impl Poseidon {
+    const CHUNK: usize = 31;
+    pub fn compress(a: jubjub::Fq, b: jubjub::Fq) -> jubjub::Fq {
+        static CONSTANTS: OnceLock<PoseidonConstants<jubjub::Fq, U2>> = OnceLock::new();
+        let constants = CONSTANTS.get_or_init(PoseidonConstants::new);
+        neptune::Poseidon::new_with_preimage(&[a, b], constants).hash()
+    }
+    fn element(chunk: &[u8]) -> jubjub::Fq {
+        let mut bytes = [0; 32];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        bytes[31] = chunk.len() as u8;
+        jubjub::Fq::from_bytes(&bytes).unwrap()
+    }
+    fn marker(count: usize) -> jubjub::Fq {
+        let mut bytes = [0; 32];
+        bytes[..8].copy_from_slice(&(count as u64).to_le_bytes());
+        bytes[31] = 0x20;
+        jubjub::Fq::from_bytes(&bytes).unwrap()
+    }
+    fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            self.state = Poseidon::compress(self.state, Poseidon::element(&self.buffer));
+            self.buffer.clear();
+        }
+    }
+}
+#[cfg(feature = "jubjub")]
+impl RingHash for Poseidon {
+    fn init() -> Poseidon {
+        Poseidon {
+            state: jubjub::Fq::zero(),
+            buffer: Vec::new(),
+        }
+    }
+    fn absorb(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+        let full = self.buffer.len() / Poseidon::CHUNK * Poseidon::CHUNK;
+        for chunk in self.buffer[..full].chunks(Poseidon::CHUNK) {
+            self.state = Poseidon::compress(self.state, Poseidon::element(chunk));
+        }
+        self.buffer.drain(..full);
+    }
+    fn finalize_wide(mut self) -> [u8; 64] {
+        self.flush();
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(&Poseidon::compress(self.state, jubjub::Fq::zero()).to_bytes());
+        bytes[32..].copy_from_slice(&Poseidon::compress(self.state, jubjub::Fq::one()).to_bytes());
+        bytes
+    }
+    fn absorb_elements(&mut self, elements: &[[u8; 32]]) {
+        self.flush();
+        self.state = Poseidon::compress(self.state, Poseidon::marker(elements.len()));
+        for element in elements {
+            let mut wide = [0; 64];
+            wide[..32].copy_from_slice(element);
+            self.state = Poseidon::compress(self.state, jubjub::Fq::from_bytes_wide(&wide));
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Sha512;
+    use sha3::Keccak256;
+    use sha3::Shake256;
+    #[test]
+    fn finalize_wide() {
+        assert_eq!(
+            <Sha512 as RingHash>::init()
+                .chain_absorb(b"ab")
+                .chain_absorb(b"c")
+                .finalize_wide(),
+            <[u8; 64]>::from(Sha512::digest(b"abc"))
+        );
+        let mut bytes = [0; 64];
+        Shake256::default()
+            .chain(b"abc")
+            .finalize_xof()
+            .read(&mut bytes);
+        assert_eq!(
+            Xof::<Shake256>::init()
+                .chain_absorb(b"ab")
+                .chain_absorb(b"c")
+                .finalize_wide(),
+            bytes
+        );
+        let wide = Wide::<Keccak256>::init()
+            .chain_absorb(b"ab")
+            .chain_absorb(b"c")
+            .finalize_wide();
+        assert_eq!(wide[..32], Keccak256::digest(b"abc\x00")[..]);
+        assert_eq!(wide[32..], Keccak256::digest(b"abc\x01")[..]);
+    }
+    #[cfg(feature = "jubjub")]
+    #[test]
+    fn poseidon() {
+        let message = [7u8; 100];
+        let wide = Poseidon::init().chain_absorb(message).finalize_wide();
+        assert_eq!(
+            Poseidon::init()
+                .chain_absorb(&message[..30])
+                .chain_absorb(&message[30..])
+                .finalize_wide(),
+            wide
+        );
+        assert_ne!(
+            Poseidon::init()
+                .chain_absorb(&message[..99])
+                .finalize_wide(),
+            wide
+        );
+        assert_ne!(
+            Poseidon::init().chain_absorb([0]).finalize_wide(),
+            Poseidon::init().finalize_wide()
+        );
+        let mut state = jubjub::Fq::zero();
+        for chunk in message.chunks(31) {
+            state = Poseidon::compress(state, Poseidon::element(chunk));
+        }
+        assert_eq!(
+            wide[..32],
+            Poseidon::compress(state, jubjub::Fq::zero()).to_bytes()
+        );
+        assert_eq!(
+            wide[32..],
+            Poseidon::compress(state, jubjub::Fq::one()).to_bytes()
+        );
+        let elements = [
+            jubjub::Fq::from(7).to_bytes(),
+            (-jubjub::Fq::one()).to_bytes(),
+        ];
+        let mut hash = Poseidon::init().chain_absorb(b"ab");
+        hash.absorb_elements(&elements);
+        let state = Poseidon::compress(jubjub::Fq::zero(), Poseidon::element(b"ab"));
+        let state = Poseidon::compress(state, Poseidon::marker(2));
+        let state = Poseidon::compress(state, jubjub::Fq::from(7));
+        let state = Poseidon::compress(state, -jubjub::Fq::one());
+        assert_eq!(
+            hash.clone().finalize_wide()[..32],
+            Poseidon::compress(state, jubjub::Fq::zero()).to_bytes()
+        );
+        assert_ne!(
+            hash.finalize_wide(),
+            Poseidon::init()
+                .chain_absorb(b"ab")
+                .chain_absorb(elements.concat())
+                .finalize_wide()
+        );
+    }
+    #[cfg(feature = "jubjub")]
+    #[test]
+    fn poseidon_neptune() {
+        let mut bytes = [0; 32];
+        for (chunk, limb) in bytes.chunks_mut(8).zip([
+            0x2e203c369a02e7ff,
+            0xa6fba9339d05a69d,
+            0x739e0fd902efe161,
+            0x396508d75e76a56bu64,
+        ]) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        assert_eq!(
+            Poseidon::compress(jubjub::Fq::zero(), jubjub::Fq::one()).to_bytes(),
+            bytes
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::elligator;
+use crate::Error;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::Scalar;
+use digest::core_api::BlockSizeUser;
+use digest::typenum::U64;
+use digest::Digest;
+#[cfg(feature = "secp256k1")]
+use k256::elliptic_curve::hash2curve::ExpandMsgXmd;
+#[cfg(feature = "secp256k1")]
+use k256::elliptic_curve::hash2curve::GroupDigest;
+use sha2::Sha512;
+pub const DST_POINT: &[u8] = b"ring-signature-V3-ristretto255_XMD:SHA-512_R255MAP_RO_";
+pub const DST_SCALAR: &[u8] = b"ring-signature-V3-ristretto255_XMD:SHA-512_H2S_";
+pub fn expand_message_xmd<Hash: Digest + BlockSizeUser>(
+    msg: &[u8],
+    dst: &[u8],
+    len: usize,
+) -> Result<Vec<u8>, Error> {
+    let ell = len.div_ceil(<Hash as Digest>::output_size());
+    if ell > 255 || len > 65535 {
+        return Err(Error::InvalidExpandLength);
+    }
+    let dst = if dst.len() > 255 {
+        Hash::new()
+            .chain_update(b"H2C-OVERSIZE-DST-")
+            .chain_update(dst)
+            .finalize()
+            .to_vec()
+    } else {
+        dst.to_vec()
+    };
+    let dst_prime = [dst.as_slice(), &[dst.len() as u8]].concat();
+    let b_0 = Hash::new()
+        .chain_update(vec![0; Hash::block_size()])
+        .chain_update(msg)
+        .chain_update((len as u16).to_be_bytes())
+        .chain_update([0])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut b_i = Hash::new()
+        .chain_update(&b_0)
+        .chain_update([1])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform = b_i.to_vec();
+    for i in 2..=ell {
+        let xor = b_0
+            .iter()
+            .zip(b_i.iter())
+            .map(|(a, b)| a ^ b)
+            .collect::<Vec<_>>();
+        b_i = Hash::new()
+            .chain_update(xor)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform.extend_from_slice(&b_i);
+    }
+    uniform.truncate(len);
+    Ok(uniform)
+}
+pub fn hash_to_ristretto255<Hash: Digest<OutputSize = U64> + BlockSizeUser>(
+    msg: &[u8],
+    dst: &[u8],
+) -> RistrettoPoint {
+    map_to_ristretto255(&uniform_bytes::<Hash>(msg, dst))
+}
+pub fn map_to_ristretto255(uniform: &[u8; 64]) -> RistrettoPoint {
+    RistrettoPoint::from_uniform_bytes(uniform)
+}
+pub fn hash_to_edwards25519(msg: &[u8], dst: &[u8]) -> EdwardsPoint {
+    let uniform = expand_message_xmd::<Sha512>(msg, dst, 96).unwrap();
+    (elligator::map_to_edwards25519(&uniform[..48])
+        + elligator::map_to_edwards25519(&uniform[48..]))
+    .mul_by_cofactor()
+}
+#[cfg(feature = "secp256k1")]
+pub fn hash_to_secp256k1(msg: &[u8], dst: &[u8]) -> k256::ProjectivePoint {
+    k256::Secp256k1::hash_from_bytes::<ExpandMsgXmd<sha2::Sha256>>(&[msg], &[dst]).unwrap()
+}
+pub fn hash_to_scalar<Hash: Digest<OutputSize = U64> + BlockSizeUser>(
+    msg: &[u8],
+    dst: &[u8],
+) -> Scalar {
+    Scalar::from_bytes_mod_order_wide(&uniform_bytes::<Hash>(msg, dst))
+}
+fn uniform_bytes<Hash: Digest<OutputSize = U64> + BlockSizeUser>(
+    msg: &[u8],
+    dst: &[u8],
+) -> [u8; 64] {
+    expand_message_xmd::<Hash>(msg, dst, 64)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const DST: &[u8] = b"QUUX-V01-CS02-with-expander-SHA512-256";
+    #[test]
+    fn expand_message_xmd_sha512() {
+        for (msg, len, uniform) in [
+            (
+                "",
+                32,
+                "6b9a7312411d92f921c6f68ca0b6380730a1a4d982c507211a90964c394179ba",
+            ),
+            (
+                "abc",
+                32,
+                "0da749f12fbe5483eb066a5f595055679b976e93abe9be6f0f6318bce7aca8dc",
+            ),
+            (
+                "abcdef0123456789",
+                32,
+                "087e45a86e2939ee8b91100af1583c4938e0f5fc6c9db4b107b83346bc967f58",
+            ),
+            (
+                "",
+                128,
+                "41b037d1734a5f8df225dd8c7de38f851efdb45c372887be655212d07251b921b052b62eaed99b46f72f2ef4cc96bfaf254ebbbec091e1a3b9e4fb5e5b619d2e0c5414800a1d882b62bb5cd1778f098b8eb6cb399d5d9d18f5d5842cf5d13d7eb00a7cff859b605da678b318bd0e65ebff70bec88c753b159a805d2c89c55961",
+            ),
+            (
+                "abc",
+                128,
+                "7f1dddd13c08b543f2e2037b14cefb255b44c83cc397c1786d975653e36a6b11bdd7732d8b38adb4a0edc26a0cef4bb45217135456e58fbca1703cd6032cb1347ee720b87972d63fbf232587043ed2901bce7f22610c0419751c065922b488431851041310ad659e4b23520e1772ab29dcdeb2002222a363f0c2b1c972b3efe1",
+            ),
+        ] {
+            assert_eq!(
+                hex::encode(expand_message_xmd::<Sha512>(msg.as_bytes(), DST, len).unwrap()),
+                uniform
+            );
+        }
+        assert_eq!(
+            expand_message_xmd::<Sha512>(b"", DST, 255 * 64 + 1),
+            Err(Error::InvalidExpandLength)
+        );
+    }
+    #[test]
+    fn map_to_ristretto255_rfc9496() {
+        for (uniform, point) in [
+            (
+                "5d1be09e3d0c82fc538112490e35701979d99e06ca3e2b5b54bffe8b4dc772c14d98b696a1bbfb5ca32c436cc61c16563790306c79eaca7705668b47dffe5bb6",
+                "3066f82a1a747d45120d1740f14358531a8f04bbffe6a819f86dfe50f44a0a46",
+            ),
+            (
+                "f116b34b8f17ceb56e8732a60d913dd10cce47a6d53bee9204be8b44f6678b270102a56902e2488c46120e9276cfe54638286b9e4b3cdb470b542d46c2068d38",
+                "f26e5b6f7d362d2d2a94c5d0e7602cb4773c95a2e5c31a64f133189fa76ed61b",
+            ),
+            (
+                "8422e1bbdaab52938b81fd602effb6f89110e1e57208ad12d9ad767e2e25510c27140775f9337088b982d83d7fcf0b2fa1edffe51952cbe7365e95c86eaf325c",
+                "006ccd2a9e6867e6a2c5cea83d3302cc9de128dd2a9a57dd8ee7b9d7ffe02826",
+            ),
+            (
+                "ac22415129b61427bf464e17baee8db65940c233b98afce8d17c57beeb7876c2150d15af1cb1fb824bbd14955f2b57d08d388aab431a391cfc33d5bafb5dbbaf",
+                "f8f0c87cf237953c5890aec3998169005dae3eca1fbb04548c635953c817f92a",
+            ),
+            (
+                "165d697a1ef3d5cf3c38565beefcf88c0f282b8e7dbd28544c483432f1cec7675debea8ebb4e5fe7d6f6e5db15f15587ac4d4d4a1de7191e0c1ca6664abcc413",
+                "ae81e7dedf20a497e10c304a765c1767a42d6e06029758d2d7e8ef7cc4c41179",
+            ),
+            (
+                "a836e6c9a9ca9f1e8d486273ad56a78c70cf18f0ce10abb1c7172ddd605d7fd2979854f47ae1ccf204a33102095b4200e5befc0465accc263175485f0e17ea5c",
+                "e2705652ff9f5e44d3e841bf1c251cf7dddb77d140870d1ab2ed64f1a9ce8628",
+            ),
+            (
+                "2cdc11eaeb95daf01189417cdddbf95952993aa9cb9c640eb5058d09702c74622c9965a697a3b345ec24ee56335b556e677b30e6f90ac77d781064f866a3c982",
+                "80bd07262511cdde4863f8a7434cef696750681cb9510eea557088f76d9e5065",
+            ),
+        ] {
+            let uniform = hex::decode(uniform).unwrap();
+            assert_eq!(
+                hex::encode(
+                    map_to_ristretto255(&uniform.try_into().unwrap())
+                        .compress()
+                        .as_bytes()
+                ),
+                point
+            );
+        }
+    }
+    #[test]
+    fn hash_to_ristretto255_sha512() {
+        let uniform = expand_message_xmd::<Sha512>(b"abc", DST_POINT, 64).unwrap();
+        assert_eq!(
+            hash_to_ristretto255::<Sha512>(b"abc", DST_POINT),
+            map_to_ristretto255(&uniform.try_into().unwrap())
+        );
+        assert_ne!(
+            hash_to_ristretto255::<Sha512>(b"abc", DST_POINT),
+            hash_to_ristretto255::<Sha512>(b"abc", DST_SCALAR)
+        );
+        let uniform = expand_message_xmd::<Sha512>(b"abc", DST_SCALAR, 64).unwrap();
+        assert_eq!(
+            hash_to_scalar::<Sha512>(b"abc", DST_SCALAR),
+            Scalar::from_bytes_mod_order_wide(&uniform.try_into().unwrap())
+        );
+    }
+    #[test]
+    fn hash_to_edwards25519_sha512() {
+        for (msg, x, y) in [
+            (
+                "",
+                "3c3da6925a3c3c268448dcabb47ccde5439559d9599646a8260e47b1e4822fc6",
+                "09a6c8561a0b22bef63124c588ce4c62ea83a3c899763af26d795302e115dc21",
+            ),
+            (
+                "abc",
+                "608040b42285cc0d72cbb3985c6b04c935370c7361f4b7fbdb1ae7f8c1a8ecad",
+                "1a8395b88338f22e435bbd301183e7f20a5f9de643f11882fb237f88268a5531",
+            ),
+            (
+                "abcdef0123456789",
+                "6d7fabf47a2dc03fe7d47f7dddd21082c5fb8f86743cd020f3fb147d57161472",
+                "53060a3d140e7fbcda641ed3cf42c88a75411e648a1add71217f70ea8ec561a6",
+            ),
+        ] {
+            let mut encoding = hex::decode(y).unwrap();
+            encoding.reverse();
+            encoding[31] |= (hex::decode(x).unwrap()[31] & 1) << 7;
+            assert_eq!(
+                hex::encode(
+                    hash_to_edwards25519(
+                        msg.as_bytes(),
+                        b"QUUX-V01-CS02-with-edwards25519_XMD:SHA-512_ELL2_RO_"
+                    )
+                    .compress()
+                    .as_bytes()
+                ),
+                hex::encode(encoding)
+            );
+        }
+    }
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn hash_to_secp256k1_sha256() {
+        use k256::elliptic_curve::sec1::ToEncodedPoint;
+        const DST: &[u8] = b"QUUX-V01-CS02-with-secp256k1_XMD:SHA-256_SSWU_RO_";
+        for (msg, x, y) in [
+            (
+                "",
+                "c1cae290e291aee617ebaef1be6d73861479c48b841eaba9b7b5852ddfeb1346",
+                "64fa678e07ae116126f08b022a94af6de15985c996c3a91b64c406a960e51067",
+            ),
+            (
+                "abc",
+                "3377e01eab42db296b512293120c6cee72b6ecf9f9205760bd9ff11fb3cb2c4b",
+                "7f95890f33efebd1044d382a01b1bee0900fb6116f94688d487c6c7b9c8371f6",
+            ),
+            (
+                "abcdef0123456789",
+                "bac54083f293f1fe08e4a70137260aa90783a5cb84d3f35848b324d0674b0e3a",
+                "4436476085d4c3c4508b60fcf4389c40176adce756b398bdee27bca19758d828",
+            ),
+        ] {
+            let point = hash_to_secp256k1(msg.as_bytes(), DST).to_affine();
+            let point = point.to_encoded_point(false);
+            assert_eq!(hex::encode(point.x().unwrap()), x);
+            assert_eq!(hex::encode(point.y().unwrap()), y);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use crate::tagged_message;
+use crate::Error;
+use crate::Group;
+use crate::RingHash;
+use crate::Ristretto255;
+#[cfg(feature = "secp256k1")]
+use crate::Secp256k1;
+use crate::Version;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::Scalar;
+use rand_core::CryptoRngCore;
+use serde::de;
+use serde::ser::SerializeTuple;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::collections::HashSet;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+#[derive(Clone, Eq, PartialEq)]
+pub struct SecretKey<G: Group = Ristretto255>(pub(crate) G::Scalar);
+impl<G: Group> SecretKey<G> {
+    pub fn random(rng: &mut impl CryptoRngCore) -> SecretKey<G> {
+        SecretKey(G::scalar_random(rng))
+    }
+    pub fn from_bytes(bytes: [u8; 32]) -> Result<SecretKey<G>, Error> {
+        G::scalar_from_canonical(bytes)
+            .map(SecretKey)
+            .ok_or(Error::InvalidSecretKey)
+    }
+    pub fn to_bytes(&self) -> [u8; 32] {
+        G::scalar_to_bytes(&self.0)
+    }
+    pub fn public_key(&self) -> PublicKey<G> {
+        PublicKey(G::generator() * self.0)
+    }
+    pub fn image<Hash: RingHash>(&self) -> KeyImage<G> {
+        self.image_with_version::<Hash>(Version::V2)
+    }
+    pub fn image_with_version<Hash: RingHash>(&self, version: Version) -> KeyImage<G> {
+        let key = self.public_key().to_bytes();
+        KeyImage(G::hash_to_point::<Hash>(version, key.as_ref()) * self.0)
+    }
+    pub fn image_with_tag<Hash: RingHash>(&self, tag: &[u8]) -> KeyImage<G> {
+        self.image_with_tag_and_version::<Hash>(tag, Version::V2)
+    }
+    pub fn image_with_tag_and_version<Hash: RingHash>(
+        &self,
+        tag: &[u8],
+        version: Version,
+    ) -> KeyImage<G> {
+        let key = self.public_key().to_bytes();
+        KeyImage(G::hash_to_point::<Hash>(version, &tagged_message(tag, key.as_ref())) * self.0)
+    }
+}
+impl<G: Group> fmt::Debug for SecretKey<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+#[cfg(feature = "zeroize")]
+impl<G: Group> zeroize::Zeroize for SecretKey<G> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+#[cfg(feature = "zeroize")]
+impl<G: Group> Drop for SecretKey<G> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+#[cfg(feature = "zeroize")]
+impl<G: Group> zeroize::ZeroizeOnDrop for SecretKey<G> {}
+impl From<Scalar> for SecretKey {
+    fn from(scalar: Scalar) -> SecretKey {
+        SecretKey(scalar)
+    }
+}
+impl From<SecretKey> for Scalar {
+    fn from(secret: SecretKey) -> Scalar {
+        secret.0
+    }
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PublicKey<G: Group = Ristretto255>(pub(crate) G::Point);
+impl<G: Group> PublicKey<G> {
+    pub fn from_bytes(bytes: &G::Encoding) -> Result<PublicKey<G>, Error> {
+        G::point_from_bytes(bytes)
+            .map(PublicKey)
+            .ok_or(Error::InvalidPublicKey)
+    }
+    pub fn to_bytes(&self) -> G::Encoding {
+        G::point_to_bytes(&self.0)
+    }
+}
+#[cfg(feature = "secp256k1")]
+impl PublicKey<Secp256k1> {
+    pub fn from_sec1(bytes: &[u8]) -> Result<PublicKey<Secp256k1>, Error> {
+        k256::PublicKey::from_sec1_bytes(bytes)
+            .map(|key| PublicKey(key.to_projective()))
+            .map_err(|_| Error::InvalidPublicKey)
+    }
+    pub fn to_sec1(&self, compress: bool) -> Vec<u8> {
+        use k256::elliptic_curve::sec1::ToEncodedPoint;
+        self.0
+            .to_affine()
+            .to_encoded_point(compress)
+            .as_bytes()
+            .to_vec()
+    }
+}
+impl From<RistrettoPoint> for PublicKey {
+    fn from(point: RistrettoPoint) -> PublicKey {
+        PublicKey(point)
+    }
+}
+impl From<PublicKey> for RistrettoPoint {
+    fn from(key: PublicKey) -> RistrettoPoint {
+        key.0
+    }
+}
+impl<G: Group> Serialize for PublicKey<G> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_encoding::<G, S>(&self.to_bytes(), serializer)
+    }
+}
+impl<'de, G: Group> Deserialize<'de> for PublicKey<G> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<PublicKey<G>, D::Error> {
+        PublicKey::from_bytes(&deserialize_encoding::<G, D>(deserializer)?)
+            .map_err(de::Error::custom)
+    }
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KeyImage<G: Group = Ristretto255>(pub(crate) G::Point);
+impl<G: Group> KeyImage<G> {
+    pub fn from_bytes(bytes: &G::Encoding) -> Result<KeyImage<G>, Error> {
+        G::point_from_bytes(bytes)
+            .map(KeyImage)
+            .ok_or(Error::InvalidKeyImage { index: 0 })
+    }
+    pub fn to_bytes(&self) -> G::Encoding {
+        G::point_to_bytes(&self.0)
+    }
+}
+impl From<RistrettoPoint> for KeyImage {
+    fn from(point: RistrettoPoint) -> KeyImage {
+        KeyImage(point)
+    }
+}
+impl From<KeyImage> for RistrettoPoint {
+    fn from(image: KeyImage) -> RistrettoPoint {
+        image.0
+    }
+}
+impl<G: Group> Serialize for KeyImage<G> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_encoding::<G, S>(&self.to_bytes(), serializer)
+    }
+}
+impl<'de, G: Group> Deserialize<'de> for KeyImage<G> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<KeyImage<G>, D::Error> {
+        KeyImage::from_bytes(&deserialize_encoding::<G, D>(deserializer)?)
+            .map_err(de::Error::custom)
+    }
+}
+fn serialize_encoding<G: Group, S: Serializer>(
+    bytes: &G::Encoding,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut tuple = serializer.serialize_tuple(bytes.as_ref().len())?;
+    for byte in bytes.as_ref() {
+        tuple.serialize_element(byte)?;
+    }
+    tuple.end()
+}
+fn deserialize_encoding<'de, G: Group, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<G::Encoding, D::Error> {
+    struct Visitor<G>(PhantomData<G>);
+    impl<'de, G: Group> de::Visitor<'de> for Visitor<G> {
+        type Value = G::Encoding;
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("an encoded point")
+        }
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<G::Encoding, A::Error> {
+            let mut bytes = Vec::new();
+            while let Some(byte) = seq.next_element::<u8>()? {
+                bytes.push(byte);
+            }
+            G::Encoding::try_from(&bytes).map_err(|_| de::Error::invalid_length(bytes.len(), &self))
+        }
+    }
+    let len = G::point_to_bytes(&G::generator()).as_ref().len();
+    deserializer.deserialize_tuple(len, Visitor::<G>(PhantomData))
+}
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent, bound = "")]
+pub struct Ring<G: Group = Ristretto255>(pub(crate) Vec<PublicKey<G>>);
+impl<G: Group> Ring<G> {
+    pub fn new(keys: Vec<PublicKey<G>>) -> Ring<G> {
+        Ring(keys)
+    }
+    pub fn from_bytes(bytes: &[G::Encoding]) -> Result<Ring<G>, Error> {
+        bytes
+            .iter()
+            .enumerate()
+            .map(|(row, bytes)| {
+                PublicKey::from_bytes(bytes).map_err(|_| Error::InvalidPoint { row, col: 0 })
+            })
+            .collect()
+    }
+    pub fn to_bytes(&self) -> Vec<G::Encoding> {
+        self.0.iter().map(PublicKey::to_bytes).collect()
+    }
+    pub fn into_vec(self) -> Vec<PublicKey<G>> {
+        self.0
+    }
+    pub fn canonicalize(&mut self) {
+        self.0.sort_by_cached_key(PublicKey::to_bytes);
+    }
+    pub fn is_canonical(&self) -> bool {
+        is_canonical(self.0.chunks(1))
+    }
+    pub fn validate(&self) -> Result<(), Error> {
+        validate(self.0.chunks(1))
+    }
+}
+#[cfg(feature = "secp256k1")]
+impl Ring<Secp256k1> {
+    pub fn from_sec1(keys: &[impl AsRef<[u8]>]) -> Result<Ring<Secp256k1>, Error> {
+        keys.iter()
+            .enumerate()
+            .map(|(row, bytes)| {
+                PublicKey::from_sec1(bytes.as_ref())
+                    .map_err(|_| Error::InvalidPoint { row, col: 0 })
+            })
+            .collect()
+    }
+}
+pub(crate) fn canonicalize<G: Group>(rings: &mut [Vec<PublicKey<G>>]) {
+    rings.sort_by_cached_key(|ring| ring.iter().map(PublicKey::to_bytes).collect::<Vec<_>>());
+}
+pub(crate) fn is_canonical<'a, G: Group>(
+    rings: impl IntoIterator<Item = &'a [PublicKey<G>]>,
+) -> bool {
+    let rings = rings
+        .into_iter()
+        .map(|ring| ring.iter().map(PublicKey::to_bytes).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    rings.windows(2).all(|pair| pair[0] < pair[1])
+}
+pub(crate) fn validate<'a, G: Group>(
+    rings: impl IntoIterator<Item = &'a [PublicKey<G>]>,
+) -> Result<(), Error> {
+    let identity = G::point_to_bytes(&G::identity());
+    let mut seen = HashSet::new();
+    for (row, ring) in rings.into_iter().enumerate() {
+        let ring = ring.iter().map(PublicKey::to_bytes).collect::<Vec<_>>();
+        if let Some(col) = ring.iter().position(|bytes| *bytes == identity) {
+            return Err(Error::IdentityMember { row, col });
+        }
+        if !seen.insert(ring) {
+            return Err(Error::DuplicateMember { row });
+        }
+    }
+    Ok(())
+}
+impl<G: Group> Deref for Ring<G> {
+    type Target = [PublicKey<G>];
+    fn deref(&self) -> &[PublicKey<G>] {
+        &self.0
+    }
+}
+impl<G: Group> From<Vec<PublicKey<G>>> for Ring<G> {
+    fn from(keys: Vec<PublicKey<G>>) -> Ring<G> {
+        Ring(keys)
+    }
+}
+impl From<Vec<RistrettoPoint>> for Ring {
+    fn from(points: Vec<RistrettoPoint>) -> Ring {
+        points.into_iter().collect()
+    }
+}
+impl<G: Group> FromIterator<PublicKey<G>> for Ring<G> {
+    fn from_iter<I: IntoIterator<Item = PublicKey<G>>>(iter: I) -> Ring<G> {
+        Ring(iter.into_iter().collect())
+    }
+}
+impl FromIterator<RistrettoPoint> for Ring {
+    fn from_iter<I: IntoIterator<Item = RistrettoPoint>>(iter: I) -> Ring {
+        Ring(iter.into_iter().map(PublicKey).collect())
+    }
+}
+impl<G: Group> IntoIterator for Ring<G> {
+    type Item = PublicKey<G>;
+    type IntoIter = std::vec::IntoIter<PublicKey<G>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+impl<'a, G: Group> IntoIterator for &'a Ring<G> {
+    type Item = &'a PublicKey<G>;
+    type IntoIter = std::slice::Iter<'a, PublicKey<G>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_random;
+    use crate::KeyImage;
+    use crate::PublicKey;
+    use crate::Ring;
+    use crate::SecretKey;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    #[test]
+    fn from_bytes() {
+        let secret = SecretKey::random(&mut OsRng);
+        let public = secret.public_key();
+        let image = secret.image::<Sha512>();
+        assert_eq!(SecretKey::from_bytes(secret.to_bytes()), Ok(secret));
+        assert_eq!(PublicKey::from_bytes(&public.to_bytes()), Ok(public));
+        assert_eq!(KeyImage::from_bytes(&image.to_bytes()), Ok(image));
+        assert_eq!(
+            SecretKey::from_bytes([0xff; 32]),
+            Err(Error::InvalidSecretKey)
+        );
+        assert_eq!(
+            PublicKey::from_bytes(&[0xff; 32]),
+            Err(Error::InvalidPublicKey)
+        );
+        assert_eq!(
+            KeyImage::from_bytes(&[0xff; 32]),
+            Err(Error::InvalidKeyImage { index: 0 })
+        );
+        let ring = (0..3).map(|_| point_random(&mut OsRng)).collect::<Ring>();
+        let mut bytes = ring.to_bytes();
+        assert_eq!(Ring::from_bytes(&bytes), Ok(ring));
+        bytes[1] = [0xff; 32];
+        assert_eq!(
+            Ring::from_bytes(&bytes),
+            Err(Error::InvalidPoint { row: 1, col: 0 })
+        );
+    }
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize() {
+        use zeroize::Zeroize;
+        let mut secret = SecretKey::random(&mut OsRng);
+        secret.zeroize();
+        assert_eq!(secret.to_bytes(), [0; 32]);
+    }
+    #[test]
+    fn serde() {
+        let ring = (0..3).map(|_| point_random(&mut OsRng)).collect::<Ring>();
+        let bytes = bincode::serialize(&ring).unwrap();
+        assert_eq!(bincode::serialize(&ring.to_bytes()).unwrap(), bytes);
+        assert_eq!(bincode::deserialize::<Ring>(&bytes).unwrap(), ring);
+        let bytes = bincode::serialize(&[0xffu8; 32]).unwrap();
+        assert!(bincode::deserialize::<PublicKey>(&bytes).is_err());
+        assert!(bincode::deserialize::<KeyImage>(&bytes).is_err());
+    }
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn sec1() {
+        let mut bytes = [0; 32];
+        bytes[31] = 1;
+        let secret = super::SecretKey::<Secp256k1>::from_bytes(bytes).unwrap();
+        let public = secret.public_key();
+        let compressed = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let uncompressed = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+        assert_eq!(hex::encode(public.to_sec1(true)), compressed);
+        assert_eq!(hex::encode(public.to_sec1(false)), uncompressed);
+        assert_eq!(hex::encode(public.to_bytes()), compressed);
+        assert_eq!(
+            super::PublicKey::from_sec1(&hex::decode(uncompressed).unwrap()),
+            Ok(public)
+        );
+        assert_eq!(
+            super::PublicKey::from_sec1(&[0; 33]),
+            Err(Error::InvalidPublicKey)
+        );
+        let mut keys = vec![public.to_sec1(false), public.to_sec1(true)];
+        assert_eq!(
+            super::Ring::from_sec1(&keys),
+            Ok(super::Ring::new(vec![public, public]))
+        );
+        keys[1].pop();
+        assert_eq!(
+            super::Ring::from_sec1(&keys),
+            Err(Error::InvalidPoint { row: 1, col: 0 })
+        );
+    }
+}
@@ -1,14 +1,91 @@
+#[cfg(feature = "parallel")]
+macro_rules! iter {
+    ($iter:expr) => {
+        rayon::iter::IntoParallelIterator::into_par_iter($iter)
+    };
+}
+#[cfg(not(feature = "parallel"))]
+macro_rules! iter {
+    ($iter:expr) => {
+        IntoIterator::into_iter($iter)
+    };
+}
+pub mod accountable;
+pub mod arcturus;
 pub mod blsag;
+pub mod cache;
 pub mod clsag;
+mod elligator;
+pub mod error;
+pub mod group;
+pub mod hash;
+pub mod hash_to_curve;
+pub mod keys;
+pub mod linkable_one_of_many;
+mod members;
 pub mod mlsag;
+pub mod one_of_many;
 pub mod sag;
+pub mod threshold;
+pub mod traceable;
+pub mod transcript;
+mod version;
+pub use cache::HashToPointCache;
+pub use cache::LruCache;
 pub use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
 pub use curve25519_dalek::ristretto::CompressedRistretto;
 pub use curve25519_dalek::RistrettoPoint;
 pub use curve25519_dalek::Scalar;
 pub use digest::typenum::U64;
 pub use digest::Digest;
+pub use error::Error;
+pub use group::Ed25519;
+pub use group::Group;
+#[cfg(feature = "jubjub")]
+pub use group::Jubjub;
+#[cfg(feature = "jubjub")]
+pub use group::JubjubScalar;
+pub use group::Ristretto255;
+#[cfg(feature = "secp256k1")]
+pub use group::Secp256k1;
+#[cfg(feature = "jubjub")]
+pub use hash::Poseidon;
+pub use hash::RingHash;
+pub use hash::Wide;
+pub use hash::Xof;
 pub use rand_core::CryptoRngCore;
+use sha2::Sha512;
+pub use transcript::Transcript;
+pub(crate) use version::legacy_version;
+pub(crate) use version::supported_version;
+pub use version::Version;
+pub type KeyImage = keys::KeyImage<Ristretto255>;
+pub type PublicKey = keys::PublicKey<Ristretto255>;
+pub type Ring = keys::Ring<Ristretto255>;
+pub type SecretKey = keys::SecretKey<Ristretto255>;
+#[cfg(feature = "zeroize")]
+pub(crate) use zeroize::Zeroizing;
+#[cfg(not(feature = "zeroize"))]
+pub(crate) struct Zeroizing<T>(T);
+#[cfg(not(feature = "zeroize"))]
+impl<T> Zeroizing<T> {
+    pub(crate) fn new(value: T) -> Zeroizing<T> {
+        Zeroizing(value)
+    }
+}
+#[cfg(not(feature = "zeroize"))]
+impl<T> std::ops::Deref for Zeroizing<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+#[cfg(not(feature = "zeroize"))]
+impl<T> std::ops::DerefMut for Zeroizing<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
 pub fn point_from_slice(bytes: &[u8; 32]) -> Option<RistrettoPoint> {
     CompressedRistretto::from_slice(bytes).unwrap().decompress()
 }
@@ -17,18 +94,41 @@ pub fn point_random(rng: &mut impl CryptoRngCore) -> RistrettoPoint {
     rng.fill_bytes(&mut bytes);
     RistrettoPoint::from_uniform_bytes(&bytes)
 }
-pub fn point_hash<Hash: Digest<OutputSize = U64>>(point: RistrettoPoint) -> RistrettoPoint {
-    RistrettoPoint::from_uniform_bytes(
-        &Hash::new()
-            .chain_update(point.compress().as_bytes())
-            .finalize()
-            .into(),
-    )
+pub fn point_hash<Hash: RingHash>(point: RistrettoPoint) -> RistrettoPoint {
+    point_hash_with_version::<Hash>(Version::V2, point)
+}
+pub fn point_hash_with_version<Hash: RingHash>(
+    version: Version,
+    point: RistrettoPoint,
+) -> RistrettoPoint {
+    point_hash_bytes::<Hash>(version, point.compress().as_bytes())
+}
+pub(crate) fn point_hash_bytes<Hash: RingHash>(version: Version, bytes: &[u8]) -> RistrettoPoint {
+    match version {
+        Version::V3 => {
+            hash_to_curve::hash_to_ristretto255::<Sha512>(bytes, hash_to_curve::DST_POINT)
+        }
+        _ => RistrettoPoint::from_uniform_bytes(&Hash::init().chain_absorb(bytes).finalize_wide()),
+    }
+}
+pub(crate) fn tagged_point_hash_bytes<Hash: RingHash>(
+    version: Version,
+    tag: &[u8],
+    bytes: &[u8; 32],
+) -> RistrettoPoint {
+    point_hash_bytes::<Hash>(version, &tagged_message(tag, bytes))
+}
+pub(crate) fn tagged_message(tag: &[u8], bytes: &[u8]) -> Vec<u8> {
+    let mut message = b"ring-signature/tag".to_vec();
+    message.extend((tag.len() as u64).to_le_bytes());
+    message.extend(tag);
+    message.extend(bytes);
+    message
 }
 pub fn scalar_random(rng: &mut impl CryptoRngCore) -> Scalar {
-    let mut bytes = [0u8; 32];
-    rng.fill_bytes(&mut bytes);
-    Scalar::from_bytes_mod_order(bytes)
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    rng.fill_bytes(&mut *bytes);
+    Scalar::from_bytes_mod_order(*bytes)
 }
 pub fn scalar_from_canonical(bytes: [u8; 32]) -> Option<Scalar> {
     Scalar::from_canonical_bytes(bytes).into()
@@ -36,16 +136,37 @@ pub fn scalar_from_canonical(bytes: [u8; 32]) -> Option<Scalar> {
 pub fn scalar_zero() -> Scalar {
     Scalar::from_bytes_mod_order([0; 32])
 }
-pub fn scalar_from_hash<Hash: Digest<OutputSize = U64>>(hash: Hash) -> Scalar {
-    Scalar::from_bytes_mod_order_wide(&hash.finalize().into())
+pub fn scalar_from_hash<Hash: RingHash>(hash: Hash) -> Scalar {
+    Scalar::from_bytes_mod_order_wide(&hash.finalize_wide())
+}
+pub fn image<Hash: RingHash>(secret: &Scalar) -> RistrettoPoint {
+    image_with_version::<Hash>(Version::V2, secret)
+}
+pub fn image_with_version<Hash: RingHash>(version: Version, secret: &Scalar) -> RistrettoPoint {
+    let a = secret * RISTRETTO_BASEPOINT_POINT;
+    let b = point_hash_with_version::<Hash>(version, a);
+    secret * b
 }
-pub fn image<Hash: Digest<OutputSize = U64>>(secret: &Scalar) -> RistrettoPoint {
+pub fn image_with_tag<Hash: RingHash>(tag: &[u8], secret: &Scalar) -> RistrettoPoint {
+    image_with_tag_and_version::<Hash>(Version::V2, tag, secret)
+}
+pub fn image_with_tag_and_version<Hash: RingHash>(
+    version: Version,
+    tag: &[u8],
+    secret: &Scalar,
+) -> RistrettoPoint {
     let a = secret * RISTRETTO_BASEPOINT_POINT;
-    let b = point_hash::<Hash>(a);
+    let b = tagged_point_hash_bytes::<Hash>(version, tag, a.compress().as_bytes());
     secret * b
 }
-pub fn images<Hash: Digest<OutputSize = U64>>(secrets: &[Scalar]) -> Vec<RistrettoPoint> {
+pub fn images<Hash: RingHash>(secrets: &[Scalar]) -> Vec<RistrettoPoint> {
+    images_with_version::<Hash>(Version::V2, secrets)
+}
+pub fn images_with_version<Hash: RingHash>(
+    version: Version,
+    secrets: &[Scalar],
+) -> Vec<RistrettoPoint> {
     let a = secrets[0] * RISTRETTO_BASEPOINT_POINT;
-    let b = point_hash::<Hash>(a);
+    let b = point_hash_with_version::<Hash>(version, a);
     secrets.iter().map(|scalar| scalar * b).collect()
 }
@@ -0,0 +1,393 @@
+use crate::members::Members;
+use crate::one_of_many;
+use crate::one_of_many::Prover;
+use crate::scalar_random;
+use crate::Error;
+use crate::KeyImage;
+use crate::Ring;
+use crate::RingHash;
+use crate::SecretKey;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
+use crate::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::IsIdentity;
+use curve25519_dalek::traits::MultiscalarMul;
+use rand::Rng;
+use rand_core::CryptoRngCore;
+use serde::Deserialize;
+use serde::Serialize;
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LinkableOneOfMany {
+    pub a: [u8; 32],
+    pub b: [u8; 32],
+    pub c: [u8; 32],
+    pub d: [u8; 32],
+    pub x: Vec<[u8; 32]>,
+    pub y: Vec<[u8; 32]>,
+    pub e: Vec<[u8; 32]>,
+    pub f: Vec<Vec<[u8; 32]>>,
+    pub z_a: [u8; 32],
+    pub z_c: [u8; 32],
+    pub z_0: [u8; 32],
+    pub z_1: [u8; 32],
+    pub z_e: [u8; 32],
+    pub ring: Ring,
+    pub image: KeyImage,
+}
+impl LinkableOneOfMany {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey,
+        ring: Ring,
+        data: impl AsRef<[u8]>,
+    ) -> Result<LinkableOneOfMany, Error> {
+        LinkableOneOfMany::sign_with_context::<Hash>(rng, secret, ring, b"", data)
+    }
+    pub fn sign_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey,
+        mut ring: Ring,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<LinkableOneOfMany, Error> {
+        let secret_index = rng.gen_range(0..=ring.len());
+        ring.0.insert(secret_index, secret.public_key());
+        LinkableOneOfMany::sign_with_index::<Hash>(rng, secret, ring, secret_index, context, data)
+    }
+    pub fn sign_with_index<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey,
+        ring: Ring,
+        secret_index: usize,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<LinkableOneOfMany, Error> {
+        if secret_index >= ring.len() {
+            return Err(Error::SignerIndexOutOfRange);
+        }
+        if ring[secret_index] != secret.public_key() {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let members = Members::new(ring.chunks(1))?.hash::<Hash>(Version::V2, 1);
+        let image = KeyImage::from(secret.0 * members.hashes(secret_index)[0]);
+        let m = one_of_many::digits(members.len());
+        let (h, f_point) = LinkableOneOfMany::generators::<Hash>(m);
+        let prover = Prover::new(rng, &h, m, &[secret_index]);
+        let rho = Zeroizing::new((0..m).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+        let tau = Zeroizing::new((0..m).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+        let x = prover.mix(
+            &[0],
+            &rho,
+            |k| members.points[k % members.len()],
+            RISTRETTO_BASEPOINT_POINT,
+        );
+        let y = prover.mix(
+            &[0],
+            &tau,
+            |k| members.hashes(k % members.len())[0],
+            image.0,
+        );
+        let z_0 = Zeroizing::new(
+            rho.iter()
+                .map(|rho| -rho)
+                .chain([secret.0])
+                .collect::<Vec<_>>(),
+        );
+        let z_1 = Zeroizing::new(
+            tau.iter()
+                .map(|tau| -tau)
+                .chain([secret.0.invert()])
+                .collect::<Vec<_>>(),
+        );
+        let product = Zeroizing::new(one_of_many::poly_mul(&z_0, &z_1));
+        let e_blinding = Zeroizing::new((0..2 * m).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+        let e = (0..2 * m)
+            .map(|d| {
+                RistrettoPoint::multiscalar_mul(
+                    [product[d], e_blinding[d]],
+                    [RISTRETTO_BASEPOINT_POINT, f_point],
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut signature = LinkableOneOfMany {
+            a: prover.commitments[0].compress().to_bytes(),
+            b: prover.commitments[1].compress().to_bytes(),
+            c: prover.commitments[2].compress().to_bytes(),
+            d: prover.commitments[3].compress().to_bytes(),
+            x: one_of_many::compress(&x),
+            y: one_of_many::compress(&y),
+            e: one_of_many::compress(&e),
+            f: Vec::new(),
+            z_a: [0; 32],
+            z_c: [0; 32],
+            z_0: [0; 32],
+            z_1: [0; 32],
+            z_e: [0; 32],
+            ring,
+            image,
+        };
+        let xi = LinkableOneOfMany::transcript::<Hash>(context, &members.bytes, &signature, data)?
+            .challenge_scalar(b"xi");
+        (signature.f, signature.z_a, signature.z_c) = prover.respond(xi);
+        signature.z_0 = one_of_many::poly_eval(&z_0, xi).to_bytes();
+        signature.z_1 = one_of_many::poly_eval(&z_1, xi).to_bytes();
+        signature.z_e = one_of_many::poly_eval(&e_blinding, xi).to_bytes();
+        Ok(signature)
+    }
+    pub fn verify<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(b"", data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if self.image.0.is_identity() {
+            return Err(Error::InvalidKeyImage { index: 0 });
+        }
+        let members = Members::new(self.ring.chunks(1))?.hash::<Hash>(Version::V2, 1);
+        let m = one_of_many::digits(members.len());
+        if self.x.len() != m
+            || self.y.len() != m
+            || self.e.len() != 2 * m
+            || self.f.len() != m
+            || self.f.iter().any(|f_j| f_j.len() != one_of_many::N - 1)
+        {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let bits = [
+            one_of_many::point(&self.a)?,
+            one_of_many::point(&self.b)?,
+            one_of_many::point(&self.c)?,
+            one_of_many::point(&self.d)?,
+        ];
+        let x = one_of_many::points(&self.x)?;
+        let y = one_of_many::points(&self.y)?;
+        let e = one_of_many::points(&self.e)?;
+        let z_a = one_of_many::scalar(0, self.z_a)?;
+        let z_c = one_of_many::scalar(1, self.z_c)?;
+        let z_0 = one_of_many::scalar(2, self.z_0)?;
+        let z_1 = one_of_many::scalar(3, self.z_1)?;
+        let z_e = one_of_many::scalar(4, self.z_e)?;
+        let xi = LinkableOneOfMany::transcript::<Hash>(context, &members.bytes, self, data)?
+            .challenge_scalar(b"xi");
+        let f = one_of_many::expand(&self.f, xi, 5)?;
+        let (h, f_point) = LinkableOneOfMany::generators::<Hash>(m);
+        let weights = one_of_many::weights(&f, members.len());
+        let hashes = (0..members.len())
+            .map(|i| members.hashes(i)[0])
+            .collect::<Vec<_>>();
+        let powers = one_of_many::powers(xi, 2 * m + 1);
+        let mut equations = one_of_many::bits_equations(&h, bits, &f, z_a, z_c, xi).to_vec();
+        equations.push(one_of_many::mix_equation(
+            &weights,
+            &members.points,
+            &x,
+            xi,
+            z_0,
+            RISTRETTO_BASEPOINT_POINT,
+        ));
+        equations.push(one_of_many::mix_equation(
+            &weights,
+            &hashes,
+            &y,
+            xi,
+            z_1,
+            self.image.0,
+        ));
+        equations.push(
+            powers
+                .iter()
+                .map(|power| -power)
+                .zip(e)
+                .chain([
+                    (z_0 * z_1 - powers[2 * m], RISTRETTO_BASEPOINT_POINT),
+                    (z_e, f_point),
+                ])
+                .collect(),
+        );
+        if equations.into_iter().all(one_of_many::is_zero) {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+    pub fn link(images: &[KeyImage]) -> bool {
+        if images.is_empty() {
+            return false;
+        }
+        images.iter().skip(1).all(|image| image == &images[0])
+    }
+    fn transcript<Hash: RingHash>(
+        context: &[u8],
+        ring: &[[u8; 32]],
+        signature: &LinkableOneOfMany,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript =
+            Transcript::versioned(Version::V2, b"ring-signature/LinkableOneOfMany/v2", context)?;
+        for key in ring {
+            transcript.append_message(b"ring", key);
+        }
+        transcript.append_message(b"image", signature.image.to_bytes());
+        transcript.append_message(b"A", signature.a);
+        transcript.append_message(b"B", signature.b);
+        transcript.append_message(b"C", signature.c);
+        transcript.append_message(b"D", signature.d);
+        for x in &signature.x {
+            transcript.append_message(b"X", x);
+        }
+        for y in &signature.y {
+            transcript.append_message(b"Y", y);
+        }
+        for e in &signature.e {
+            transcript.append_message(b"E", e);
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+    fn generators<Hash: RingHash>(m: usize) -> (Vec<RistrettoPoint>, RistrettoPoint) {
+        (
+            one_of_many::generators::<Hash>(b"ring-signature/LinkableOneOfMany/v2/generator", m),
+            one_of_many::generator::<Hash>(
+                b"ring-signature/LinkableOneOfMany/v2/generator",
+                b"F",
+                0,
+            ),
+        )
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::point_random;
+    use crate::scalar_from_canonical;
+    use curve25519_dalek::Scalar;
+    use lazy_static::lazy_static;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    const DATA_0: &[u8] = b"hello from";
+    const DATA_1: &str = "zudo";
+    const X: usize = 5;
+    lazy_static! {
+        static ref SECRET_0: SecretKey = SecretKey::random(&mut OsRng);
+        static ref SECRET_1: SecretKey = SecretKey::random(&mut OsRng);
+        static ref RING_0: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
+        static ref RING_1: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
+    }
+    #[test]
+    fn sign_verify() {
+        let rng = &mut OsRng;
+        for x in [0, 1, 2, 3, 7, 8] {
+            let ring = (0..x).map(|_| point_random(&mut OsRng)).collect();
+            let a = LinkableOneOfMany::sign::<Sha512>(rng, &SECRET_0, ring, DATA_0).unwrap();
+            assert_eq!(a.x.len(), one_of_many::digits(x + 1));
+            assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        }
+        let b = LinkableOneOfMany::sign::<Sha512>(rng, &SECRET_1, RING_1.clone(), DATA_0).unwrap();
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(b.verify::<Sha512>(DATA_1), Err(Error::InvalidProof));
+    }
+    #[test]
+    fn sign_with_index() {
+        let rng = &mut OsRng;
+        let mut ring = RING_0.clone();
+        ring.0.insert(3, SECRET_0.public_key());
+        let a = LinkableOneOfMany::sign_with_index::<Sha512>(
+            rng,
+            &SECRET_0,
+            ring.clone(),
+            3,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            LinkableOneOfMany::sign_with_index::<Sha512>(
+                rng,
+                &SECRET_0,
+                ring.clone(),
+                0,
+                b"",
+                DATA_0
+            ),
+            Err(Error::SignerKeyMismatch)
+        );
+        assert_eq!(
+            LinkableOneOfMany::sign_with_index::<Sha512>(rng, &SECRET_0, ring, X + 1, b"", DATA_0),
+            Err(Error::SignerIndexOutOfRange)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let rng = &mut OsRng;
+        let a = LinkableOneOfMany::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        let mut b = a.clone();
+        b.image = SECRET_1.image::<Sha512>();
+        assert_eq!(b.verify::<Sha512>(DATA_0), Err(Error::InvalidProof));
+        let mut c = a.clone();
+        c.f[0][0] = (Scalar::ONE + scalar_from_canonical(c.f[0][0]).unwrap()).to_bytes();
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::InvalidProof));
+        let mut d = a.clone();
+        d.z_e = (Scalar::ONE + scalar_from_canonical(d.z_e).unwrap()).to_bytes();
+        assert_eq!(d.verify::<Sha512>(DATA_0), Err(Error::InvalidProof));
+        let mut e = a.clone();
+        e.x.pop();
+        assert_eq!(
+            e.verify::<Sha512>(DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut f = a.clone();
+        f.a = [0xff; 32];
+        assert_eq!(f.verify::<Sha512>(DATA_0), Err(Error::InvalidProofPoint));
+        let mut h = a.clone();
+        h.image = KeyImage::from(RistrettoPoint::default());
+        assert_eq!(
+            h.verify::<Sha512>(DATA_0),
+            Err(Error::InvalidKeyImage { index: 0 })
+        );
+        let mut g = a;
+        g.z_0 = [0xff; 32];
+        assert_eq!(
+            g.verify::<Sha512>(DATA_0),
+            Err(Error::NonCanonicalScalar { index: 2 })
+        );
+    }
+    #[test]
+    fn link() {
+        let rng = &mut OsRng;
+        let a = LinkableOneOfMany::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        let b = LinkableOneOfMany::sign::<Sha512>(rng, &SECRET_0, RING_1.clone(), DATA_1).unwrap();
+        let c = BLSAG::sign::<Sha512>(rng, &SECRET_0, RING_1.clone(), DATA_1).unwrap();
+        let d = LinkableOneOfMany::sign::<Sha512>(rng, &SECRET_1, RING_0.clone(), DATA_0).unwrap();
+        assert_eq!(a.image, SECRET_0.image::<Sha512>());
+        assert!(LinkableOneOfMany::link(&[a.image, b.image, c.image]));
+        assert!(!LinkableOneOfMany::link(&[a.image, d.image]));
+    }
+    #[test]
+    fn serde() {
+        let rng = &mut OsRng;
+        let a = LinkableOneOfMany::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA_0).unwrap();
+        let b: LinkableOneOfMany = bincode::deserialize(&bincode::serialize(&a).unwrap()).unwrap();
+        assert_eq!(a, b);
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+    }
+    #[test]
+    fn context() {
+        let rng = &mut OsRng;
+        let a = LinkableOneOfMany::sign_with_context::<Sha512>(
+            rng,
+            &SECRET_0,
+            RING_0.clone(),
+            b"zudo",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(a.verify_with_context::<Sha512>(b"zudo", DATA_0).is_ok());
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::InvalidProof));
+    }
+}
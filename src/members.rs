@@ -0,0 +1,188 @@
+use crate::cache;
+use crate::cache::point_hash_cached;
+use crate::keys::PublicKey;
+use crate::tagged_message;
+use crate::Error;
+use crate::Group;
+use crate::HashToPointCache;
+use crate::RingHash;
+use crate::Ristretto255;
+use crate::Version;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+pub(crate) struct Members<G: Group = Ristretto255> {
+    pub(crate) width: usize,
+    pub(crate) points: Vec<G::Point>,
+    pub(crate) bytes: Vec<G::Encoding>,
+    pub(crate) tag: Option<Vec<u8>>,
+    hashed: usize,
+    hashes: Vec<G::Point>,
+}
+impl<G: Group> Members<G> {
+    pub(crate) fn new<'a>(
+        rings: impl IntoIterator<Item = &'a [PublicKey<G>]>,
+    ) -> Result<Members<G>, Error> {
+        let rings = rings.into_iter().collect::<Vec<_>>();
+        if rings.is_empty() || rings[0].is_empty() {
+            return Err(Error::EmptyRing);
+        }
+        let width = rings[0].len();
+        if rings.iter().any(|ring| ring.len() != width) {
+            return Err(Error::RingWidthMismatch);
+        }
+        let points = rings
+            .iter()
+            .flat_map(|ring| ring.iter().map(|key| key.0))
+            .collect::<Vec<_>>();
+        let bytes = iter!(&points).map(G::point_to_bytes).collect::<Vec<_>>();
+        Ok(Members {
+            width,
+            points,
+            bytes,
+            tag: None,
+            hashed: 0,
+            hashes: Vec::new(),
+        })
+    }
+    pub(crate) fn keys(&self, columns: usize) -> impl Iterator<Item = &G::Encoding> {
+        self.bytes
+            .chunks(self.width)
+            .flat_map(move |ring| &ring[..columns])
+    }
+    pub(crate) fn hash<Hash: RingHash>(mut self, version: Version, columns: usize) -> Members<G> {
+        let keys = self.keys(columns).collect::<Vec<_>>();
+        self.hashes = iter!(keys)
+            .map(|key| G::hash_to_point::<Hash>(version, key.as_ref()))
+            .collect();
+        self.hashed = columns;
+        self
+    }
+    pub(crate) fn hash_tagged<Hash: RingHash>(
+        mut self,
+        version: Version,
+        tag: &[u8],
+        columns: usize,
+    ) -> Members<G> {
+        let keys = self.keys(columns).collect::<Vec<_>>();
+        self.hashes = iter!(keys)
+            .map(|key| G::hash_to_point::<Hash>(version, &tagged_message(tag, key.as_ref())))
+            .collect();
+        self.tag = Some(tag.to_vec());
+        self.hashed = columns;
+        self
+    }
+    pub(crate) fn hash_cached<Hash: RingHash + 'static>(
+        mut self,
+        version: Version,
+        columns: usize,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Members<G> {
+        cache::fill::<G, Hash>(cache, self.keys(columns).map(|key| (version, key)));
+        self.hashes = self
+            .keys(columns)
+            .map(|key| point_hash_cached::<G, Hash>(version, cache, key))
+            .collect();
+        self.hashed = columns;
+        self
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.points.len() / self.width
+    }
+    pub(crate) fn row(&self, index: usize) -> &[G::Point] {
+        &self.points[index * self.width..(index + 1) * self.width]
+    }
+    pub(crate) fn hashes(&self, index: usize) -> &[G::Point] {
+        &self.hashes[index * self.hashed..(index + 1) * self.hashed]
+    }
+}
+pub(crate) fn verify_each<T: Sync>(
+    batch: &[(&T, &[u8])],
+    verify: impl Fn(&T, &[u8]) -> Result<(), Error> + Sync,
+) -> Result<(), Vec<(usize, Error)>> {
+    failures(
+        iter!(batch)
+            .enumerate()
+            .filter_map(|(index, (signature, data))| {
+                verify(signature, data).err().map(|error| (index, error))
+            })
+            .collect(),
+    )
+}
+pub(crate) fn verify_batch<G: Group, Hash: RingHash + 'static, T: Sync>(
+    batch: &[(&T, &[u8])],
+    cache: &mut impl HashToPointCache<G>,
+    members: impl Fn(&T) -> Result<(Members<G>, Version, usize), Error> + Sync,
+    verify: impl Fn(&T, &Members<G>, &[u8]) -> Result<(), Error> + Sync,
+) -> Result<(), Vec<(usize, Error)>> {
+    let members = iter!(batch)
+        .map(|(signature, _)| members(signature))
+        .collect::<Vec<_>>();
+    cache::fill::<G, Hash>(
+        cache,
+        members
+            .iter()
+            .flatten()
+            .flat_map(|(members, version, columns)| {
+                members.keys(*columns).map(move |key| (*version, key))
+            }),
+    );
+    let members = members
+        .into_iter()
+        .map(|members| {
+            members.map(|(members, version, columns)| {
+                members.hash_cached::<Hash>(version, columns, cache)
+            })
+        })
+        .collect::<Vec<_>>();
+    failures(
+        iter!(batch)
+            .zip(iter!(&members))
+            .enumerate()
+            .filter_map(|(index, ((signature, data), members))| {
+                members
+                    .as_ref()
+                    .map_err(|error| *error)
+                    .and_then(|members| verify(signature, members, data))
+                    .err()
+                    .map(|error| (index, error))
+            })
+            .collect(),
+    )
+}
+fn failures(failed: Vec<(usize, Error)>) -> Result<(), Vec<(usize, Error)>> {
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(failed)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_hash;
+    use crate::point_random;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    use std::collections::HashMap;
+    #[test]
+    fn hash() {
+        let rings = (0..4)
+            .map(|_| (0..2).map(|_| point_random(&mut OsRng).into()).collect())
+            .collect::<Vec<Vec<PublicKey>>>();
+        let a = Members::new(rings.iter().map(Vec::as_slice))
+            .unwrap()
+            .hash::<Sha512>(Version::V2, 2);
+        let b = Members::new(rings.iter().map(Vec::as_slice))
+            .unwrap()
+            .hash_cached::<Sha512>(Version::V2, 2, &mut HashMap::new());
+        for (i, ring) in rings.iter().enumerate() {
+            let hashes = ring
+                .iter()
+                .map(|key| point_hash::<Sha512>(key.0))
+                .collect::<Vec<_>>();
+            assert_eq!(a.hashes(i), hashes);
+            assert_eq!(b.hashes(i), hashes);
+            assert_eq!(a.row(i), ring.iter().map(|key| key.0).collect::<Vec<_>>());
+        }
+    }
+}
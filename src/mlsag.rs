@@ -1,178 +1,364 @@
-use crate::point_from_slice;
-use crate::point_hash;
-use crate::scalar_from_canonical;
-use crate::scalar_from_hash;
-use crate::scalar_random;
-use crate::scalar_zero;
-use crate::RISTRETTO_BASEPOINT_POINT;
-use curve25519_dalek::ristretto::RistrettoPoint;
-use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::traits::MultiscalarMul;
-use digest::generic_array::typenum::U64;
-use digest::Digest;
+use crate::keys;
+use crate::keys::KeyImage;
+use crate::keys::PublicKey;
+use crate::keys::SecretKey;
+use crate::legacy_version;
+use crate::members;
+use crate::members::Members;
+use crate::supported_version;
+use crate::Error;
+use crate::Group;
+use crate::HashToPointCache;
+use crate::RingHash;
+use crate::Ristretto255;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
 use rand::Rng;
 use rand_core::CryptoRngCore;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct MLSAG {
+#[serde(bound = "")]
+pub struct MLSAG<G: Group = Ristretto255> {
     pub challenge: [u8; 32],
     pub responses: Vec<Vec<[u8; 32]>>,
-    pub rings: Vec<Vec<[u8; 32]>>,
-    pub images: Vec<[u8; 32]>,
+    pub rings: Vec<Vec<PublicKey<G>>>,
+    pub images: Vec<KeyImage<G>>,
+    pub version: Version,
 }
 impl MLSAG {
-    pub fn sign<Hash: Digest<OutputSize = U64> + Clone>(
+    pub fn legacy(
+        challenge: [u8; 32],
+        responses: Vec<Vec<[u8; 32]>>,
+        rings: Vec<Vec<PublicKey>>,
+        images: Vec<KeyImage>,
+    ) -> MLSAG {
+        MLSAG {
+            challenge,
+            responses,
+            rings,
+            images,
+            version: Version::Legacy,
+        }
+    }
+}
+impl<G: Group> MLSAG<G> {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        message: impl AsRef<[u8]>,
+    ) -> Result<MLSAG<G>, Error> {
+        MLSAG::sign_with_context::<Hash>(rng, secrets, rings, b"", message)
+    }
+    pub fn sign_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        context: &[u8],
+        message: impl AsRef<[u8]>,
+    ) -> Result<MLSAG<G>, Error> {
+        MLSAG::sign_with_version::<Hash>(rng, secrets, rings, Version::V2, context, message)
+    }
+    pub fn sign_with_version<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        mut rings: Vec<Vec<PublicKey<G>>>,
+        version: Version,
+        context: &[u8],
+        message: impl AsRef<[u8]>,
+    ) -> Result<MLSAG<G>, Error> {
+        let secret_index = rng.gen_range(0..=rings.len());
+        rings.insert(
+            secret_index,
+            secrets.iter().map(SecretKey::public_key).collect(),
+        );
+        MLSAG::sign_with_index::<Hash>(rng, secrets, rings, secret_index, version, context, message)
+    }
+    pub fn sign_canonical<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        mut rings: Vec<Vec<PublicKey<G>>>,
+        context: &[u8],
+        message: impl AsRef<[u8]>,
+    ) -> Result<MLSAG<G>, Error> {
+        let public_keys = secrets
+            .iter()
+            .map(SecretKey::public_key)
+            .collect::<Vec<_>>();
+        rings.push(public_keys.clone());
+        keys::canonicalize(&mut rings);
+        keys::validate(rings.iter().map(Vec::as_slice))?;
+        let secret_index = rings.iter().position(|ring| *ring == public_keys).unwrap();
+        MLSAG::sign_with_index::<Hash>(
+            rng,
+            secrets,
+            rings,
+            secret_index,
+            Version::V2,
+            context,
+            message,
+        )
+    }
+    pub fn sign_with_index<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        message: impl AsRef<[u8]>,
+    ) -> Result<MLSAG<G>, Error> {
+        MLSAG::sign_members::<Hash>(
+            rng,
+            secrets,
+            rings,
+            secret_index,
+            version,
+            context,
+            message,
+            |members| {
+                let y = members.width;
+                members.hash::<Hash>(version, y)
+            },
+        )
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign_with_index_and_cache<Hash: RingHash + 'static>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        message: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<MLSAG<G>, Error> {
+        MLSAG::sign_members::<Hash>(
+            rng,
+            secrets,
+            rings,
+            secret_index,
+            version,
+            context,
+            message,
+            |members| {
+                let y = members.width;
+                members.hash_cached::<Hash>(version, y, cache)
+            },
+        )
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn sign_members<Hash: RingHash>(
         rng: &mut impl CryptoRngCore,
-        secrets: &[Scalar],
-        mut rings: Vec<Vec<RistrettoPoint>>,
+        secrets: &[SecretKey<G>],
+        rings: Vec<Vec<PublicKey<G>>>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
         message: impl AsRef<[u8]>,
-    ) -> Option<MLSAG> {
-        let x = rings.len() + 1;
-        let y = rings[0].len();
+        hash: impl FnOnce(Members<G>) -> Members<G>,
+    ) -> Result<MLSAG<G>, Error> {
+        supported_version(version)?;
+        if secrets.is_empty() {
+            return Err(Error::EmptySecrets);
+        }
+        let x = rings.len();
+        let y = secrets.len();
+        if rings.iter().any(|ring| ring.len() != y) {
+            return Err(Error::RingWidthMismatch);
+        }
+        if secret_index >= x {
+            return Err(Error::SignerIndexOutOfRange);
+        }
         let k_points = secrets
             .iter()
-            .map(|scalar| scalar * RISTRETTO_BASEPOINT_POINT)
+            .map(SecretKey::public_key)
             .collect::<Vec<_>>();
-        let images = MLSAG::image::<Hash>(secrets);
-        let secret_index = rng.gen_range(0..x);
-        rings.insert(secret_index, k_points.clone());
-        let a: Vec<Scalar> = (0..y).map(|_| scalar_random(rng)).collect();
+        if rings[secret_index] != k_points {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let members = hash(Members::new(rings.iter().map(Vec::as_slice))?);
+        let images = secrets
+            .iter()
+            .zip(members.hashes(secret_index))
+            .map(|(secret, hash)| KeyImage(*hash * secret.0))
+            .collect::<Vec<_>>();
+        let a = Zeroizing::new(
+            (0..y)
+                .map(|_| G::scalar_random(rng))
+                .collect::<Vec<G::Scalar>>(),
+        );
         let mut responses = (0..x)
-            .map(|_| (0..y).map(|_| scalar_random(rng)).collect())
+            .map(|_| (0..y).map(|_| G::scalar_random(rng)).collect())
             .collect::<Vec<Vec<_>>>();
-        let mut challenges: Vec<Scalar> = (0..x).map(|_| scalar_zero()).collect();
-        let mut hash = Hash::new();
-        hash.update(message);
-        let mut hashes: Vec<Hash> = (0..x).map(|_| hash.clone()).collect();
+        let mut challenges: Vec<G::Scalar> = (0..x).map(|_| G::scalar_from_u64(0)).collect();
+        let transcript =
+            MLSAG::transcript::<Hash>(version, context, &members.bytes, &images, message)?;
+        let mut transcripts = (0..x).map(|_| transcript.clone()).collect::<Vec<_>>();
         for j in 0..y {
-            hashes[(secret_index + 1) % x]
-                .update((a[j] * RISTRETTO_BASEPOINT_POINT).compress().as_bytes());
-            hashes[(secret_index + 1) % x].update(
-                (a[j] * point_hash::<Hash>(k_points[j]))
-                    .compress()
-                    .as_bytes(),
-            );
+            transcripts[(secret_index + 1) % x].append_element::<G>(b"L", &(G::generator() * a[j]));
+            transcripts[(secret_index + 1) % x]
+                .append_element::<G>(b"R", &(members.hashes(secret_index)[j] * a[j]));
         }
         challenges[(secret_index + 1) % x] =
-            scalar_from_hash(hashes[(secret_index + 1) % x].clone());
+            transcripts[(secret_index + 1) % x].challenge::<G>(b"c");
         let mut i = (secret_index + 1) % x;
         loop {
             for j in 0..y {
-                hashes[(i + 1) % x].update(
-                    RistrettoPoint::multiscalar_mul(
+                transcripts[(i + 1) % x].append_element::<G>(
+                    b"L",
+                    &G::multiscalar_mul(
                         &[responses[i % x][j], challenges[i % x]],
-                        &[RISTRETTO_BASEPOINT_POINT, rings[i % x][j]],
-                    )
-                    .compress()
-                    .as_bytes(),
+                        &[G::generator(), rings[i % x][j].0],
+                    ),
                 );
-                hashes[(i + 1) % x].update(
-                    RistrettoPoint::multiscalar_mul(
+                transcripts[(i + 1) % x].append_element::<G>(
+                    b"R",
+                    &G::multiscalar_mul(
                         &[responses[i % x][j], challenges[i % x]],
-                        &[point_hash::<Hash>(rings[i % x][j]), images[j]],
-                    )
-                    .compress()
-                    .as_bytes(),
+                        &[members.hashes(i % x)[j], images[j].0],
+                    ),
                 );
             }
-            challenges[(i + 1) % x] = scalar_from_hash(hashes[(i + 1) % x].clone());
-            if secret_index >= 1 && i % x == (secret_index - 1) % x {
+            challenges[(i + 1) % x] = transcripts[(i + 1) % x].challenge::<G>(b"c");
+            if (secret_index >= 1 && i % x == (secret_index - 1) % x)
+                || (secret_index == 0 && i % x == x - 1)
+            {
                 break;
-            } else if secret_index == 0 && i % x == x - 1 {
-                break;
-            } else {
-                i = (i + 1) % x;
             }
+            i = (i + 1) % x;
         }
         for j in 0..y {
-            responses[secret_index][j] = a[j] - (challenges[secret_index] * secrets[j]);
+            responses[secret_index][j] =
+                a[j] - *Zeroizing::new(challenges[secret_index] * secrets[j].0);
         }
-        Some(MLSAG {
-            challenge: challenges[0].to_bytes(),
+        Ok(MLSAG {
+            challenge: G::scalar_to_bytes(&challenges[0]),
             responses: responses
                 .iter()
-                .map(|vec| vec.iter().map(|scalar| scalar.to_bytes()).collect())
-                .collect::<Vec<Vec<_>>>(),
-            rings: rings
-                .iter()
-                .map(|vec| {
-                    vec.iter()
-                        .map(|point| point.compress().to_bytes())
-                        .collect()
-                })
+                .map(|vec| vec.iter().map(G::scalar_to_bytes).collect())
                 .collect::<Vec<Vec<_>>>(),
-            images: images
-                .iter()
-                .map(|point| point.compress().to_bytes())
-                .collect(),
+            rings,
+            images,
+            version,
         })
     }
-    pub fn verify<Hash: Digest<OutputSize = U64> + Clone>(&self, data: impl AsRef<[u8]>) -> bool {
-        match || -> Option<bool> {
-            let rings = self
-                .rings
-                .iter()
-                .map(|vec| vec.iter().map(|bytes| point_from_slice(bytes)).collect())
-                .collect::<Option<Vec<Vec<_>>>>()?;
-            let images = self
-                .images
-                .iter()
-                .map(|bytes| point_from_slice(bytes))
-                .collect::<Option<Vec<_>>>()?;
-            let responses = self
-                .responses
-                .iter()
-                .map(|vec| {
-                    vec.iter()
-                        .map(|&bytes| scalar_from_canonical(bytes))
-                        .collect()
-                })
-                .collect::<Option<Vec<Vec<_>>>>()?;
-            let challenge_0 = scalar_from_canonical(self.challenge)?;
-            let mut challenge_1 = challenge_0;
-            let x = self.rings.len();
-            let y = self.rings[0].len();
-            for i in 0..x {
-                let mut hash = Hash::new();
-                hash.update(&data);
-                for j in 0..y {
-                    hash.update(
-                        RistrettoPoint::multiscalar_mul(
-                            &[responses[i][j], challenge_1],
-                            &[RISTRETTO_BASEPOINT_POINT, rings[i][j]],
-                        )
-                        .compress()
-                        .as_bytes(),
-                    );
-                    hash.update(
-                        RistrettoPoint::multiscalar_mul(
-                            &[responses[i][j], challenge_1],
-                            &[point_hash::<Hash>(rings[i][j]), images[j]],
-                        )
-                        .compress()
-                        .as_bytes(),
-                    );
-                }
-                challenge_1 = scalar_from_hash(hash);
-            }
-            Some(challenge_0 == challenge_1)
-        }() {
-            Some(x) => x,
-            None => return false,
+    pub fn verify<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(b"", data)
+    }
+    pub fn verify_legacy<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        legacy_version(self.version)?;
+        let members = Members::new(self.rings.iter().map(Vec::as_slice))?;
+        let y = members.width;
+        MLSAG::verify_transcript::<Hash>(
+            self.challenge,
+            &self.responses,
+            &members.hash::<Hash>(self.version, y),
+            &self.images,
+            self.version,
+            b"",
+            data,
+        )
+    }
+    pub fn verify_canonical<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if !keys::is_canonical(self.rings.iter().map(Vec::as_slice)) {
+            return Err(Error::NonCanonicalRing);
         }
+        keys::validate(self.rings.iter().map(Vec::as_slice))?;
+        self.verify_with_context::<Hash>(context, data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        let members = Members::new(self.rings.iter().map(Vec::as_slice))?;
+        let y = members.width;
+        MLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.responses,
+            &members.hash::<Hash>(self.version, y),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_with_cache<Hash: RingHash + 'static>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Error> {
+        let members = Members::new(self.rings.iter().map(Vec::as_slice))?;
+        let y = members.width;
+        MLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.responses,
+            &members.hash_cached::<Hash>(self.version, y, cache),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_batch<Hash: RingHash + 'static>(
+        batch: &[(&MLSAG<G>, &[u8])],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        MLSAG::verify_batch_with_context::<Hash>(batch, b"")
+    }
+    pub fn verify_batch_with_context<Hash: RingHash + 'static>(
+        batch: &[(&MLSAG<G>, &[u8])],
+        context: &[u8],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        MLSAG::verify_batch_with_cache::<Hash>(batch, context, &mut HashMap::new())
+    }
+    pub fn verify_batch_with_cache<Hash: RingHash + 'static>(
+        batch: &[(&MLSAG<G>, &[u8])],
+        context: &[u8],
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Vec<(usize, Error)>> {
+        members::verify_batch::<_, Hash, _>(
+            batch,
+            cache,
+            |mlsag| {
+                let members = Members::new(mlsag.rings.iter().map(Vec::as_slice))?;
+                let y = members.width;
+                Ok((members, mlsag.version, y))
+            },
+            |mlsag, members, data| {
+                MLSAG::verify_parts::<Hash>(
+                    mlsag.challenge,
+                    &mlsag.responses,
+                    members,
+                    &mlsag.images,
+                    mlsag.version,
+                    context,
+                    data,
+                )
+            },
+        )
     }
-    pub fn image<Hash: Digest<OutputSize = U64>>(secrets: &[Scalar]) -> Vec<RistrettoPoint> {
-        let x = secrets.len();
-        let publics = secrets
+    pub fn image<Hash: RingHash>(secrets: &[SecretKey<G>]) -> Vec<KeyImage<G>> {
+        MLSAG::image_with_version::<Hash>(Version::V2, secrets)
+    }
+    pub fn image_with_version<Hash: RingHash>(
+        version: Version,
+        secrets: &[SecretKey<G>],
+    ) -> Vec<KeyImage<G>> {
+        secrets
             .iter()
-            .map(|scalar| scalar * RISTRETTO_BASEPOINT_POINT)
-            .collect::<Vec<_>>();
-        (0..x)
-            .map(|i| secrets[i] * point_hash::<Hash>(publics[i]))
+            .map(|secret| secret.image_with_version::<Hash>(version))
             .collect()
     }
-    pub fn link(images: &[&[[u8; 32]]]) -> bool {
+    pub fn link(images: &[&[KeyImage<G>]]) -> bool {
         if images.is_empty() || images[0].is_empty() {
             return false;
         }
@@ -181,26 +367,203 @@ impl MLSAG {
             .skip(1)
             .all(|&slice| !slice.is_empty() && slice[0] == images[0][0])
     }
+    pub fn detach(self) -> (DetachedMLSAG<G>, Vec<Vec<PublicKey<G>>>) {
+        (
+            DetachedMLSAG {
+                challenge: self.challenge,
+                responses: self.responses,
+                images: self.images,
+                version: self.version,
+            },
+            self.rings,
+        )
+    }
+    fn verify_parts<Hash: RingHash>(
+        challenge: [u8; 32],
+        responses: &[Vec<[u8; 32]>],
+        rings: &Members<G>,
+        images: &[KeyImage<G>],
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        supported_version(version)?;
+        MLSAG::verify_transcript::<Hash>(
+            challenge, responses, rings, images, version, context, data,
+        )
+    }
+    fn verify_transcript<Hash: RingHash>(
+        challenge: [u8; 32],
+        responses: &[Vec<[u8; 32]>],
+        rings: &Members<G>,
+        images: &[KeyImage<G>],
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        let x = rings.len();
+        let y = rings.width;
+        if images.len() != y {
+            return Err(Error::ImageCountMismatch);
+        }
+        if responses.len() != x || responses.iter().any(|vec| vec.len() != y) {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let responses = responses
+            .iter()
+            .enumerate()
+            .map(|(row, vec)| {
+                vec.iter()
+                    .enumerate()
+                    .map(|(col, &bytes)| {
+                        G::scalar_from_canonical(bytes).ok_or(Error::NonCanonicalScalar {
+                            index: row * y + col,
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<_>>, _>>()?;
+        let challenge_0 =
+            G::scalar_from_canonical(challenge).ok_or(Error::NonCanonicalChallenge)?;
+        let mut challenge_1 = challenge_0;
+        let transcript = MLSAG::transcript::<Hash>(version, context, &rings.bytes, images, data)?;
+        for (i, responses) in responses.iter().enumerate() {
+            let mut transcript = transcript.clone();
+            for (((response, key), hash), image) in responses
+                .iter()
+                .zip(rings.row(i))
+                .zip(rings.hashes(i))
+                .zip(images)
+            {
+                transcript.append_element::<G>(
+                    b"L",
+                    &G::vartime_multiscalar_mul(&[*response, challenge_1], &[G::generator(), *key]),
+                );
+                transcript.append_element::<G>(
+                    b"R",
+                    &G::vartime_multiscalar_mul(&[*response, challenge_1], &[*hash, image.0]),
+                );
+            }
+            challenge_1 = transcript.challenge::<G>(b"c");
+        }
+        if challenge_0 != challenge_1 {
+            return Err(Error::ChallengeMismatch);
+        }
+        Ok(())
+    }
+    fn transcript<Hash: RingHash>(
+        version: Version,
+        context: &[u8],
+        rings: &[G::Encoding],
+        images: &[KeyImage<G>],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript = Transcript::versioned(version, b"ring-signature/MLSAG/v2", context)?;
+        if version != Version::Legacy {
+            for key in rings {
+                transcript.append_encoding::<G>(b"ring", key);
+            }
+            for image in images {
+                transcript.append_element::<G>(b"image", &image.0);
+            }
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+}
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct DetachedMLSAG<G: Group = Ristretto255> {
+    pub challenge: [u8; 32],
+    pub responses: Vec<Vec<[u8; 32]>>,
+    pub images: Vec<KeyImage<G>>,
+    pub version: Version,
+}
+impl<G: Group> DetachedMLSAG<G> {
+    pub fn attach(self, rings: Vec<Vec<PublicKey<G>>>) -> MLSAG<G> {
+        MLSAG {
+            challenge: self.challenge,
+            responses: self.responses,
+            rings,
+            images: self.images,
+            version: self.version,
+        }
+    }
+    pub fn verify_with_ring<Hash: RingHash>(
+        &self,
+        rings: &[Vec<PublicKey<G>>],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        self.verify_with_ring_and_context::<Hash>(rings, b"", data)
+    }
+    pub fn verify_with_ring_and_context<Hash: RingHash>(
+        &self,
+        rings: &[Vec<PublicKey<G>>],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        let members = Members::new(rings.iter().map(Vec::as_slice))?;
+        let y = members.width;
+        MLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.responses,
+            &members.hash::<Hash>(self.version, y),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_with_ring_and_cache<Hash: RingHash + 'static>(
+        &self,
+        rings: &[Vec<PublicKey<G>>],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+        cache: &mut impl HashToPointCache<G>,
+    ) -> Result<(), Error> {
+        let members = Members::new(rings.iter().map(Vec::as_slice))?;
+        let y = members.width;
+        MLSAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.responses,
+            &members.hash_cached::<Hash>(self.version, y, cache),
+            &self.images,
+            self.version,
+            context,
+            data,
+        )
+    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::point_random;
+    use crate::LruCache;
+    use crate::RistrettoPoint;
+    use crate::Wide;
+    use crate::Xof;
     use lazy_static::lazy_static;
     use rand_core::OsRng;
     use sha2::Sha512;
+    use sha3::Keccak256;
+    use sha3::Sha3_512;
+    use sha3::Shake256;
     const DATA_0: &[u8] = b"hello from";
     const DATA_1: &str = "zudo";
     const X: usize = 2;
     const Y: usize = 2;
+    const LEGACY: &str = "a0c85d17cd971e594ec1bb0763067dc2303395434a26c1adaf07fabac5937209020000000000000002000000000000001792bc6f3e143c23fff2c0273fa7ddfcddd37631efe407cd89d4d28b86135400bb64fb43a7f3570632c000e628b645c6605a8ef5624cfec34d10d00fa6c8350102000000000000005a12d2597f74e1bf69821bc2fd8ebe3f7eaf60886f895a91eeb810a727bdb80241e9ac6ad8efb299653f2768144a8fec76db0c4a4c415420233054b77815f90902000000000000000200000000000000c0db4d49c9ad7dfe0701beffbae1e3846cf22fc6c34c915f052408d51bc5ad485cf293697cc70839fa65663ff56df80d87593c3c78a5ee6c31c2828ad3a5dd08020000000000000010412aba80872a2de5b22505a96fb6543c0f361842f929158021dd06ac290626ee0ba6d2cc9ce0e78817710e0dfd55771f342f03fc7c4c50f13cbe58719f02450200000000000000528cfa97243ce5f6c4e57ada629b4c42c8f1f331902416ce234e6412e30c0b5dd07ad6006cc94fec5eba1292e5cefdbcf380c7d784d0e8a35774b12590469e65";
+    const V1: &str = "26f16dc940e9bf26398724c6b058837ea6a86d9ef7aed86123898650f51b8f0f020000000000000002000000000000002f3f3240149d4b54413a93eb01996994c3f8f908366722d6a5cbac60a2fc240ab6daf9ef1aee988f1d537ec8a14b0d262b70c33d427815d3afd5593b4612260502000000000000000fa417b3d41353d2bd1bba621ef305afe95d42f71b46ffba46e8b87d74f12f0a8004c9146c17ab3ba45bc12531a4ebba4e9776ec7ecbcdfbf053922d9e2d050d02000000000000000200000000000000005158ca54617ac50cb3405a8b862991cd0b69109d2420c3d0db1b0fb45dfc3bf6e9b975c5711606cf525571802c05032df79e0af73997dd245c87ee9c76321d0200000000000000dc5de455a3afe19eafbc839964f119f17386f7c91ba1b5a574b224bb52c4cf45c80af806b6554a70bc90cf533f2730245c68eb8799d9bd81d5f9e47adb16473f02000000000000009c57dfb1553171ac2d8aaa8872a445d1f6496d904174d4919363529fad2c6d5a3e3ebf0275b030218f55c8f7123e6c427f196ab204915abf642cefd19d29f60301000000";
     lazy_static! {
-        static ref SECRETS_0: Vec<Scalar> = (0..Y).map(|_| scalar_random(&mut OsRng)).collect();
-        static ref SECRETS_1: Vec<Scalar> = (0..Y).map(|_| scalar_random(&mut OsRng)).collect();
-        static ref RINGS_0: Vec<Vec<RistrettoPoint>> = (0..X)
-            .map(|_| (0..Y).map(|_| point_random(&mut OsRng)).collect())
+        static ref SECRETS_0: Vec<SecretKey> =
+            (0..Y).map(|_| SecretKey::random(&mut OsRng)).collect();
+        static ref SECRETS_1: Vec<SecretKey> =
+            (0..Y).map(|_| SecretKey::random(&mut OsRng)).collect();
+        static ref RINGS_0: Vec<Vec<PublicKey>> = (0..X)
+            .map(|_| (0..Y).map(|_| point_random(&mut OsRng).into()).collect())
             .collect();
-        static ref RINGS_1: Vec<Vec<RistrettoPoint>> = (0..X)
-            .map(|_| (0..Y).map(|_| point_random(&mut OsRng)).collect())
+        static ref RINGS_1: Vec<Vec<PublicKey>> = (0..X)
+            .map(|_| (0..Y).map(|_| point_random(&mut OsRng).into()).collect())
             .collect();
     }
     #[test]
@@ -210,10 +573,235 @@ mod tests {
         let b = MLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_1.clone(), DATA_0).unwrap();
         let c = MLSAG::sign::<Sha512>(rng, &SECRETS_1, RINGS_0.clone(), DATA_0).unwrap();
         let d = MLSAG::sign::<Sha512>(rng, &SECRETS_1, RINGS_1.clone(), DATA_0).unwrap();
-        assert!((a.verify::<Sha512>(DATA_0)));
-        assert!((b.verify::<Sha512>(DATA_0)));
-        assert!((c.verify::<Sha512>(DATA_0)));
-        assert!((d.verify::<Sha512>(DATA_0)));
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+        assert!(c.verify::<Sha512>(DATA_0).is_ok());
+        assert!(d.verify::<Sha512>(DATA_0).is_ok());
+    }
+    #[test]
+    fn sign_errors() {
+        let rng = &mut OsRng;
+        assert_eq!(
+            MLSAG::sign::<Sha512>(rng, &[], RINGS_0.clone(), DATA_0),
+            Err(Error::EmptySecrets)
+        );
+        assert_eq!(
+            MLSAG::sign::<Sha512>(rng, &SECRETS_0[..1], RINGS_0.clone(), DATA_0),
+            Err(Error::RingWidthMismatch)
+        );
+    }
+    #[test]
+    fn sign_with_index() {
+        let rng = &mut OsRng;
+        let mut rings = RINGS_0.clone();
+        rings.insert(1, SECRETS_0.iter().map(SecretKey::public_key).collect());
+        let a = MLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings.clone(),
+            1,
+            Version::V2,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert_eq!(a.rings, rings);
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            MLSAG::sign_with_index::<Sha512>(
+                rng,
+                &SECRETS_0,
+                rings.clone(),
+                0,
+                Version::V2,
+                b"",
+                DATA_0
+            ),
+            Err(Error::SignerKeyMismatch)
+        );
+        assert_eq!(
+            MLSAG::sign_with_index::<Sha512>(
+                rng,
+                &SECRETS_0,
+                rings,
+                X + 1,
+                Version::V2,
+                b"",
+                DATA_0
+            ),
+            Err(Error::SignerIndexOutOfRange)
+        );
+    }
+    #[test]
+    fn canonical() {
+        let rng = &mut OsRng;
+        let a =
+            MLSAG::sign_canonical::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), b"", DATA_0).unwrap();
+        assert!(keys::is_canonical(a.rings.iter().map(Vec::as_slice)));
+        assert!(a.verify_canonical::<Sha512>(b"", DATA_0).is_ok());
+        let public_keys = SECRETS_0
+            .iter()
+            .map(SecretKey::public_key)
+            .collect::<Vec<_>>();
+        let mut rings = a.rings.clone();
+        rings.reverse();
+        let index = rings.iter().position(|ring| *ring == public_keys).unwrap();
+        let b = MLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings,
+            index,
+            Version::V2,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            b.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::NonCanonicalRing)
+        );
+        let mut c = a.clone();
+        c.rings[0] = c.rings[1].clone();
+        assert_eq!(
+            c.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::NonCanonicalRing)
+        );
+        let mut rings = RINGS_0.clone();
+        rings.push(RINGS_0[0].clone());
+        rings.push(public_keys.clone());
+        assert!(MLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings,
+            X + 1,
+            Version::V2,
+            b"",
+            DATA_0
+        )
+        .is_ok());
+        let mut rings = RINGS_0.clone();
+        rings.push(RINGS_0[0].clone());
+        assert!(matches!(
+            MLSAG::sign_canonical::<Sha512>(rng, &SECRETS_0, rings, b"", DATA_0),
+            Err(Error::DuplicateMember { .. })
+        ));
+        let mut rings = RINGS_0.clone();
+        rings[1][1] = RistrettoPoint::default().into();
+        assert!(matches!(
+            MLSAG::sign_canonical::<Sha512>(rng, &SECRETS_0, rings.clone(), b"", DATA_0),
+            Err(Error::IdentityMember { col: 1, .. })
+        ));
+        rings.push(public_keys.clone());
+        keys::canonicalize(&mut rings);
+        let index = rings.iter().position(|ring| *ring == public_keys).unwrap();
+        let d = MLSAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings,
+            index,
+            Version::V2,
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(d.verify::<Sha512>(DATA_0).is_ok());
+        assert!(matches!(
+            d.verify_canonical::<Sha512>(b"", DATA_0),
+            Err(Error::IdentityMember { col: 1, .. })
+        ));
+    }
+    #[test]
+    fn detach() {
+        let rng = &mut OsRng;
+        let a = MLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        let (b, rings) = a.clone().detach();
+        assert!(b.verify_with_ring::<Sha512>(&rings, DATA_0).is_ok());
+        assert!(bincode::serialize(&b).unwrap().len() < bincode::serialize(&a).unwrap().len());
+        let mut reversed = rings.clone();
+        reversed.reverse();
+        assert_eq!(
+            b.verify_with_ring::<Sha512>(&reversed, DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(b.attach(rings), a);
+    }
+    #[test]
+    fn verify_batch() {
+        let rng = &mut OsRng;
+        let a = MLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        let b = MLSAG::sign::<Sha512>(rng, &SECRETS_1, RINGS_0.clone(), DATA_0).unwrap();
+        let c = MLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_1.clone(), DATA_0).unwrap();
+        assert!(MLSAG::verify_batch::<Sha512>(&[(&a, DATA_0), (&b, DATA_0), (&c, DATA_0)]).is_ok());
+        let mut d = b.clone();
+        d.challenge = [0xff; 32];
+        assert_eq!(
+            MLSAG::verify_batch::<Sha512>(&[(&a, DATA_0), (&d, DATA_0), (&c, DATA_1.as_bytes())]),
+            Err(vec![
+                (1, Error::NonCanonicalChallenge),
+                (2, Error::ChallengeMismatch)
+            ])
+        );
+    }
+    #[test]
+    fn cache() {
+        let rng = &mut OsRng;
+        let mut cache = LruCache::new(64);
+        let mut rings = RINGS_0.clone();
+        rings.push(SECRETS_0.iter().map(SecretKey::public_key).collect());
+        let a = MLSAG::sign_with_index_and_cache::<Sha512>(
+            rng,
+            &SECRETS_0,
+            rings.clone(),
+            X,
+            Version::V2,
+            b"",
+            DATA_0,
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(cache.len(), (X + 1) * Y);
+        assert!(a
+            .verify_with_cache::<Sha512>(b"", DATA_0, &mut cache)
+            .is_ok());
+        let b = MLSAG::sign_with_index_and_cache::<Sha3_512>(
+            rng,
+            &SECRETS_0,
+            rings,
+            X,
+            Version::V2,
+            b"",
+            DATA_0,
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(cache.len(), 2 * (X + 1) * Y);
+        assert!(b.verify::<Sha3_512>(DATA_0).is_ok());
+        assert!(a
+            .verify_with_cache::<Sha512>(b"", DATA_0, &mut cache)
+            .is_ok());
+        assert_eq!(
+            b.verify_with_cache::<Sha512>(b"", DATA_0, &mut cache),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let rng = &mut OsRng;
+        let a = MLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        assert_eq!(a.verify::<Sha512>(DATA_1), Err(Error::ChallengeMismatch));
+        let mut b = a.clone();
+        b.images.pop();
+        assert_eq!(b.verify::<Sha512>(DATA_0), Err(Error::ImageCountMismatch));
+        let mut c = a.clone();
+        c.rings[1].pop();
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::RingWidthMismatch));
+        let mut d = a.clone();
+        d.responses[1][1] = [0xff; 32];
+        assert_eq!(
+            d.verify::<Sha512>(DATA_0),
+            Err(Error::NonCanonicalScalar { index: 3 })
+        );
     }
     #[test]
     fn link() {
@@ -230,4 +818,148 @@ mod tests {
         assert!((!MLSAG::link(&[&a.images, &e.images])));
         assert!((!MLSAG::link(&[&a.images, &f.images])));
     }
+    #[test]
+    #[allow(deprecated)]
+    fn legacy() {
+        let (challenge, responses, rings, images) =
+            bincode::deserialize(&hex::decode(LEGACY).unwrap()).unwrap();
+        let a = MLSAG::legacy(challenge, responses, rings, images);
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        assert!(a.verify_legacy::<Sha512>(DATA_0).is_ok());
+        let mut b = a.clone();
+        b.version = Version::V1;
+        assert_eq!(
+            b.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        let rng = &mut OsRng;
+        let mut c = MLSAG::sign::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        c.version = Version::Legacy;
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        assert_eq!(
+            MLSAG::verify_batch::<Sha512>(&[(&c, DATA_0)]),
+            Err(vec![(0, Error::UnsupportedVersion)])
+        );
+        let (detached, rings) = c.clone().detach();
+        assert_eq!(
+            detached.verify_with_ring::<Sha512>(&rings, DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(
+            c.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        for version in [Version::Legacy, Version::V1] {
+            assert_eq!(
+                MLSAG::sign_with_version::<Sha512>(
+                    rng,
+                    &SECRETS_0,
+                    RINGS_0.clone(),
+                    version,
+                    b"",
+                    DATA_0
+                ),
+                Err(Error::UnsupportedVersion)
+            );
+        }
+    }
+    #[test]
+    #[allow(deprecated)]
+    fn context() {
+        let a: MLSAG = bincode::deserialize(&hex::decode(V1).unwrap()).unwrap();
+        assert_eq!(a.version, Version::V1);
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::UnsupportedVersion));
+        assert!(a.verify_legacy::<Sha512>(DATA_0).is_ok());
+        assert_eq!(
+            a.verify_with_context::<Sha512>(b"zudo", DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        let rng = &mut OsRng;
+        let b =
+            MLSAG::sign_with_context::<Sha512>(rng, &SECRETS_0, RINGS_0.clone(), b"zudo", DATA_0)
+                .unwrap();
+        assert_eq!(b.version, Version::V2);
+        assert!(b.verify_with_context::<Sha512>(b"zudo", DATA_0).is_ok());
+        assert_eq!(
+            b.verify_legacy::<Sha512>(DATA_0),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(b.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+        assert_eq!(
+            b.verify_with_context::<Sha512>(b"odu", DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn version() {
+        let rng = &mut OsRng;
+        let a = MLSAG::sign_with_version::<Sha512>(
+            rng,
+            &SECRETS_0,
+            RINGS_0.clone(),
+            Version::V3,
+            b"zudo",
+            DATA_0,
+        )
+        .unwrap();
+        assert_eq!(a.version, Version::V3);
+        assert!(a.verify_with_context::<Sha512>(b"zudo", DATA_0).is_ok());
+        assert!(a
+            .verify_with_cache::<Sha512>(b"zudo", DATA_0, &mut LruCache::new(0))
+            .is_ok());
+        assert_eq!(
+            a.images,
+            MLSAG::image_with_version::<Sha512>(Version::V3, &SECRETS_0)
+        );
+        assert_ne!(a.images, MLSAG::image::<Sha512>(&SECRETS_0));
+        let mut b = a.clone();
+        b.version = Version::V2;
+        assert_eq!(
+            b.verify_with_context::<Sha512>(b"zudo", DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn ring_hash() {
+        let rng = &mut OsRng;
+        let a = MLSAG::sign::<Xof<Shake256>>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        let b = MLSAG::sign::<Wide<Keccak256>>(rng, &SECRETS_0, RINGS_0.clone(), DATA_0).unwrap();
+        assert!(a.verify::<Xof<Shake256>>(DATA_0).is_ok());
+        assert!(b.verify::<Wide<Keccak256>>(DATA_0).is_ok());
+        assert_eq!(
+            a.verify::<Wide<Keccak256>>(DATA_0),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(a.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+    }
+    fn group<G: Group>() {
+        let rng = &mut OsRng;
+        let secrets = (0..Y)
+            .map(|_| SecretKey::<G>::random(rng))
+            .collect::<Vec<_>>();
+        let mut rings = (0..X)
+            .map(|_| {
+                (0..Y)
+                    .map(|_| SecretKey::<G>::random(rng).public_key())
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+        rings.push(secrets.iter().map(SecretKey::public_key).collect());
+        let a = MLSAG::sign_with_index::<Sha512>(rng, &secrets, rings, X, Version::V2, b"", DATA_0)
+            .unwrap();
+        assert!(a.verify::<Sha512>(DATA_0).is_ok());
+        assert_eq!(a.verify::<Sha512>(DATA_1), Err(Error::ChallengeMismatch));
+        assert_eq!(a.images, MLSAG::image::<Sha512>(&secrets));
+        let bytes = bincode::serialize(&a).unwrap();
+        assert_eq!(bincode::deserialize::<MLSAG<G>>(&bytes).unwrap(), a);
+    }
+    #[test]
+    fn groups() {
+        group::<Ristretto255>();
+        group::<crate::Ed25519>();
+        #[cfg(feature = "secp256k1")]
+        group::<crate::Secp256k1>();
+        #[cfg(feature = "jubjub")]
+        group::<crate::Jubjub>();
+    }
 }
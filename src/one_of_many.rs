@@ -0,0 +1,632 @@
+use crate::scalar_from_canonical;
+use crate::scalar_random;
+use crate::scalar_zero;
+use crate::Error;
+use crate::RingHash;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
+use crate::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::IsIdentity;
+use curve25519_dalek::traits::MultiscalarMul;
+use curve25519_dalek::traits::VartimeMultiscalarMul;
+use curve25519_dalek::Scalar;
+use rand_core::CryptoRngCore;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
+pub(crate) const N: usize = 2;
+pub(crate) type Terms = Vec<(Scalar, RistrettoPoint)>;
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct OneOfMany {
+    pub a: [u8; 32],
+    pub b: [u8; 32],
+    pub c: [u8; 32],
+    pub d: [u8; 32],
+    pub x: Vec<[u8; 32]>,
+    pub f: Vec<Vec<[u8; 32]>>,
+    pub z_a: [u8; 32],
+    pub z_c: [u8; 32],
+    pub z: [u8; 32],
+}
+impl OneOfMany {
+    pub fn prove<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        commitments: &[RistrettoPoint],
+        index: usize,
+        blinding: &Scalar,
+        data: impl AsRef<[u8]>,
+    ) -> Result<OneOfMany, Error> {
+        OneOfMany::prove_with_context::<Hash>(rng, commitments, index, blinding, b"", data)
+    }
+    pub fn prove_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        commitments: &[RistrettoPoint],
+        index: usize,
+        blinding: &Scalar,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<OneOfMany, Error> {
+        if commitments.is_empty() {
+            return Err(Error::EmptyRing);
+        }
+        if index >= commitments.len() {
+            return Err(Error::SignerIndexOutOfRange);
+        }
+        if commitments[index] != blinding * RISTRETTO_BASEPOINT_POINT {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let m = digits(commitments.len());
+        let h = generators::<Hash>(b"ring-signature/OneOfMany/v2/generator", m);
+        let prover = Prover::new(rng, &h, m, &[index]);
+        let rho = Zeroizing::new((0..m).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+        let x = prover.mix(
+            &[0],
+            &rho,
+            |k| commitments[k % commitments.len()],
+            RISTRETTO_BASEPOINT_POINT,
+        );
+        let mut proof = OneOfMany {
+            a: prover.commitments[0].compress().to_bytes(),
+            b: prover.commitments[1].compress().to_bytes(),
+            c: prover.commitments[2].compress().to_bytes(),
+            d: prover.commitments[3].compress().to_bytes(),
+            x: compress(&x),
+            f: Vec::new(),
+            z_a: [0; 32],
+            z_c: [0; 32],
+            z: [0; 32],
+        };
+        let xi = OneOfMany::transcript::<Hash>(context, commitments, &proof, data)?
+            .challenge_scalar(b"xi");
+        (proof.f, proof.z_a, proof.z_c) = prover.respond(xi);
+        let z = Zeroizing::new(
+            rho.iter()
+                .map(|rho| -rho)
+                .chain([*blinding])
+                .collect::<Vec<_>>(),
+        );
+        proof.z = poly_eval(&z, xi).to_bytes();
+        Ok(proof)
+    }
+    pub fn verify<Hash: RingHash>(
+        &self,
+        commitments: &[RistrettoPoint],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(commitments, b"", data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        commitments: &[RistrettoPoint],
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if self
+            .equations::<Hash>(commitments, context, data.as_ref())?
+            .into_iter()
+            .all(is_zero)
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+    pub fn verify_batch<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        batch: &[(&OneOfMany, &[RistrettoPoint], &[u8])],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        OneOfMany::verify_batch_with_context::<Hash>(rng, batch, b"")
+    }
+    pub fn verify_batch_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        batch: &[(&OneOfMany, &[RistrettoPoint], &[u8])],
+        context: &[u8],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        let equations = iter!(batch)
+            .map(|(proof, commitments, data)| proof.equations::<Hash>(commitments, context, data))
+            .collect::<Vec<_>>();
+        let mut failed = equations
+            .iter()
+            .enumerate()
+            .filter_map(|(index, equations)| equations.as_ref().err().map(|error| (index, *error)))
+            .collect::<Vec<_>>();
+        let combined = equations
+            .into_iter()
+            .flatten()
+            .flatten()
+            .flat_map(|terms| {
+                let weight = scalar_random(rng);
+                terms
+                    .into_iter()
+                    .map(move |(scalar, point)| (weight * scalar, point))
+            })
+            .collect::<Vec<_>>();
+        if !is_zero(combined) {
+            failed.extend(
+                iter!(batch)
+                    .enumerate()
+                    .filter_map(|(index, (proof, commitments, data))| {
+                        match proof.equations::<Hash>(commitments, context, data) {
+                            Ok(equations) => (!equations.into_iter().all(is_zero))
+                                .then_some((index, Error::InvalidProof)),
+                            Err(_) => None,
+                        }
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            failed.sort_by_key(|(index, _)| *index);
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(failed)
+        }
+    }
+    fn equations<Hash: RingHash>(
+        &self,
+        commitments: &[RistrettoPoint],
+        context: &[u8],
+        data: &[u8],
+    ) -> Result<Vec<Terms>, Error> {
+        if commitments.is_empty() {
+            return Err(Error::EmptyRing);
+        }
+        let m = digits(commitments.len());
+        if self.x.len() != m || self.f.len() != m || self.f.iter().any(|f_j| f_j.len() != N - 1) {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let bits = [
+            point(&self.a)?,
+            point(&self.b)?,
+            point(&self.c)?,
+            point(&self.d)?,
+        ];
+        let x = points(&self.x)?;
+        let z_a = scalar(0, self.z_a)?;
+        let z_c = scalar(1, self.z_c)?;
+        let z = scalar(2, self.z)?;
+        let xi = OneOfMany::transcript::<Hash>(context, commitments, self, data)?
+            .challenge_scalar(b"xi");
+        let f = expand(&self.f, xi, 3)?;
+        let h = generators::<Hash>(b"ring-signature/OneOfMany/v2/generator", m);
+        let mut equations = bits_equations(&h, bits, &f, z_a, z_c, xi).to_vec();
+        equations.push(mix_equation(
+            &weights(&f, commitments.len()),
+            commitments,
+            &x,
+            xi,
+            z,
+            RISTRETTO_BASEPOINT_POINT,
+        ));
+        Ok(equations)
+    }
+    fn transcript<Hash: RingHash>(
+        context: &[u8],
+        commitments: &[RistrettoPoint],
+        proof: &OneOfMany,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript =
+            Transcript::versioned(Version::V2, b"ring-signature/OneOfMany/v2", context)?;
+        for commitment in commitments {
+            transcript.append_point(b"commitment", commitment);
+        }
+        transcript.append_message(b"A", proof.a);
+        transcript.append_message(b"B", proof.b);
+        transcript.append_message(b"C", proof.c);
+        transcript.append_message(b"D", proof.d);
+        for x in &proof.x {
+            transcript.append_message(b"X", x);
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+}
+pub(crate) struct Prover {
+    m: usize,
+    a: Zeroizing<Vec<Vec<Scalar>>>,
+    sigma: Zeroizing<Vec<Vec<Scalar>>>,
+    blindings: Zeroizing<Vec<Scalar>>,
+    p: Zeroizing<Vec<Vec<Vec<Scalar>>>>,
+    pub(crate) commitments: [RistrettoPoint; 4],
+}
+impl Prover {
+    pub(crate) fn new(
+        rng: &mut impl CryptoRngCore,
+        h: &[RistrettoPoint],
+        m: usize,
+        indices: &[usize],
+    ) -> Prover {
+        let sigma = Zeroizing::new(
+            indices
+                .iter()
+                .flat_map(|&index| decompose(index, m))
+                .map(|l_j| {
+                    (0..N)
+                        .map(|i| Scalar::from((l_j == i) as u64))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>(),
+        );
+        let a = Zeroizing::new(
+            (0..indices.len() * m)
+                .map(|_| {
+                    let mut a_j = (1..N).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+                    a_j.insert(0, -a_j.iter().sum::<Scalar>());
+                    a_j
+                })
+                .collect::<Vec<_>>(),
+        );
+        let blindings = Zeroizing::new((0..4).map(|_| scalar_random(rng)).collect::<Vec<_>>());
+        let commit = |values: Vec<Scalar>, blinding: Scalar| {
+            RistrettoPoint::multiscalar_mul(
+                values.iter().chain([&blinding]),
+                h.iter().chain([&RISTRETTO_BASEPOINT_POINT]),
+            )
+        };
+        let commitments = [
+            commit(a.concat(), blindings[0]),
+            commit(sigma.concat(), blindings[1]),
+            commit(
+                a.iter()
+                    .zip(sigma.iter())
+                    .flat_map(|(a_j, sigma_j)| {
+                        a_j.iter()
+                            .zip(sigma_j)
+                            .map(|(a, sigma)| a * (Scalar::ONE - sigma - sigma))
+                    })
+                    .collect(),
+                blindings[2],
+            ),
+            commit(a.concat().iter().map(|a| -(a * a)).collect(), blindings[3]),
+        ];
+        let p = Zeroizing::new(
+            (0..indices.len())
+                .map(|u| {
+                    (0..N.pow(m as u32))
+                        .map(|k| {
+                            decompose(k, m).iter().enumerate().fold(
+                                vec![Scalar::ONE],
+                                |p, (j, &k_j)| {
+                                    poly_mul(&p, &[a[u * m + j][k_j], sigma[u * m + j][k_j]])
+                                },
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>(),
+        );
+        Prover {
+            m,
+            a,
+            sigma,
+            blindings,
+            p,
+            commitments,
+        }
+    }
+    pub(crate) fn mix(
+        &self,
+        inputs: &[usize],
+        blindings: &[Scalar],
+        member: impl Fn(usize) -> RistrettoPoint,
+        base: RistrettoPoint,
+    ) -> Vec<RistrettoPoint> {
+        let len = N.pow(self.m as u32);
+        (0..self.m)
+            .map(|j| {
+                RistrettoPoint::multiscalar_mul(
+                    (0..len)
+                        .map(|k| inputs.iter().map(|&u| self.p[u][k][j]).sum::<Scalar>())
+                        .chain([blindings[j]]),
+                    (0..len).map(&member).chain([base]),
+                )
+            })
+            .collect()
+    }
+    pub(crate) fn respond(&self, xi: Scalar) -> (Vec<Vec<[u8; 32]>>, [u8; 32], [u8; 32]) {
+        (
+            self.a
+                .iter()
+                .zip(self.sigma.iter())
+                .map(|(a_j, sigma_j)| {
+                    (1..N)
+                        .map(|i| (sigma_j[i] * xi + a_j[i]).to_bytes())
+                        .collect()
+                })
+                .collect(),
+            (self.blindings[0] + xi * self.blindings[1]).to_bytes(),
+            (xi * self.blindings[2] + self.blindings[3]).to_bytes(),
+        )
+    }
+}
+pub(crate) fn bits_equations(
+    h: &[RistrettoPoint],
+    bits: [RistrettoPoint; 4],
+    f: &[Vec<Scalar>],
+    z_a: Scalar,
+    z_c: Scalar,
+    xi: Scalar,
+) -> [Terms; 2] {
+    let commit = |values: Vec<Scalar>, blinding: Scalar| {
+        values
+            .into_iter()
+            .chain([blinding])
+            .map(|value| -value)
+            .zip(h.iter().copied().chain([RISTRETTO_BASEPOINT_POINT]))
+            .collect::<Terms>()
+    };
+    let mut first = vec![(Scalar::ONE, bits[0]), (xi, bits[1])];
+    first.extend(commit(f.concat(), z_a));
+    let mut second = vec![(xi, bits[2]), (Scalar::ONE, bits[3])];
+    second.extend(commit(
+        f.concat().iter().map(|f| f * (xi - f)).collect(),
+        z_c,
+    ));
+    [first, second]
+}
+pub(crate) fn mix_equation(
+    weights: &[Scalar],
+    members: &[RistrettoPoint],
+    mixed: &[RistrettoPoint],
+    xi: Scalar,
+    z: Scalar,
+    base: RistrettoPoint,
+) -> Terms {
+    weights
+        .iter()
+        .copied()
+        .zip(members.iter().copied())
+        .chain(
+            powers(xi, mixed.len())
+                .into_iter()
+                .map(|power| -power)
+                .zip(mixed.iter().copied()),
+        )
+        .chain([(-z, base)])
+        .collect()
+}
+pub(crate) fn weights(f: &[Vec<Scalar>], len: usize) -> Vec<Scalar> {
+    let mut weights = vec![scalar_zero(); len];
+    for k in 0..N.pow(f.len() as u32) {
+        weights[k % len] += decompose(k, f.len())
+            .iter()
+            .enumerate()
+            .map(|(j, &k_j)| f[j][k_j])
+            .product::<Scalar>();
+    }
+    weights
+}
+pub(crate) fn expand(
+    f: &[Vec<[u8; 32]>],
+    xi: Scalar,
+    offset: usize,
+) -> Result<Vec<Vec<Scalar>>, Error> {
+    f.iter()
+        .enumerate()
+        .map(|(j, f_j)| {
+            let mut f_j = f_j
+                .iter()
+                .enumerate()
+                .map(|(i, &bytes)| scalar(offset + j * (N - 1) + i, bytes))
+                .collect::<Result<Vec<_>, _>>()?;
+            f_j.insert(0, xi - f_j.iter().sum::<Scalar>());
+            Ok(f_j)
+        })
+        .collect()
+}
+pub(crate) fn is_zero(terms: Terms) -> bool {
+    let (scalars, points): (Vec<_>, Vec<_>) = terms.into_iter().unzip();
+    RistrettoPoint::vartime_multiscalar_mul(scalars, points).is_identity()
+}
+pub(crate) fn generators<Hash: RingHash>(label: &[u8], m: usize) -> Vec<RistrettoPoint> {
+    (0..m * N)
+        .map(|index| generator::<Hash>(label, b"H", index))
+        .collect()
+}
+pub(crate) fn generator<Hash: RingHash>(label: &[u8], name: &[u8], index: usize) -> RistrettoPoint {
+    RistrettoPoint::from_uniform_bytes(
+        &Hash::init()
+            .chain_absorb(label)
+            .chain_absorb(name)
+            .chain_absorb((index as u64).to_le_bytes())
+            .finalize_wide(),
+    )
+}
+pub(crate) fn point(bytes: &[u8; 32]) -> Result<RistrettoPoint, Error> {
+    crate::point_from_slice(bytes).ok_or(Error::InvalidProofPoint)
+}
+pub(crate) fn points(bytes: &[[u8; 32]]) -> Result<Vec<RistrettoPoint>, Error> {
+    bytes.iter().map(point).collect()
+}
+pub(crate) fn scalar(index: usize, bytes: [u8; 32]) -> Result<Scalar, Error> {
+    scalar_from_canonical(bytes).ok_or(Error::NonCanonicalScalar { index })
+}
+pub(crate) fn compress(points: &[RistrettoPoint]) -> Vec<[u8; 32]> {
+    points
+        .iter()
+        .map(|point| point.compress().to_bytes())
+        .collect()
+}
+pub(crate) fn digits(len: usize) -> usize {
+    let mut m = 1;
+    while N.pow(m as u32) < len {
+        m += 1;
+    }
+    m
+}
+pub(crate) fn decompose(mut k: usize, m: usize) -> Vec<usize> {
+    (0..m)
+        .map(|_| {
+            let digit = k % N;
+            k /= N;
+            digit
+        })
+        .collect()
+}
+pub(crate) fn powers(x: Scalar, count: usize) -> Vec<Scalar> {
+    (0..count)
+        .scan(Scalar::ONE, |power, _| {
+            let current = *power;
+            *power *= x;
+            Some(current)
+        })
+        .collect()
+}
+pub(crate) fn poly_mul(a: &[Scalar], b: &[Scalar]) -> Vec<Scalar> {
+    let mut product = vec![scalar_zero(); a.len() + b.len() - 1];
+    for (i, a) in a.iter().enumerate() {
+        for (j, b) in b.iter().enumerate() {
+            product[i + j] += a * b;
+        }
+    }
+    product
+}
+pub(crate) fn poly_eval(coefficients: &[Scalar], x: Scalar) -> Scalar {
+    coefficients
+        .iter()
+        .rev()
+        .fold(scalar_zero(), |sum, coefficient| sum * x + coefficient)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_random;
+    use lazy_static::lazy_static;
+    use rand::Rng;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    const DATA_0: &[u8] = b"hello from";
+    const DATA_1: &[u8] = b"zudo";
+    const X: usize = 6;
+    lazy_static! {
+        static ref BLINDING: Scalar = scalar_random(&mut OsRng);
+        static ref COMMITMENTS: Vec<RistrettoPoint> = {
+            let mut commitments = (0..X).map(|_| point_random(&mut OsRng)).collect::<Vec<_>>();
+            commitments[X / 2] = *BLINDING * RISTRETTO_BASEPOINT_POINT;
+            commitments
+        };
+    }
+    #[test]
+    fn prove_verify() {
+        let rng = &mut OsRng;
+        for x in [1, 2, 3, 4, 9] {
+            let blinding = scalar_random(rng);
+            let index = rng.gen_range(0..x);
+            let mut commitments = (0..x).map(|_| point_random(rng)).collect::<Vec<_>>();
+            commitments[index] = blinding * RISTRETTO_BASEPOINT_POINT;
+            let a =
+                OneOfMany::prove::<Sha512>(rng, &commitments, index, &blinding, DATA_0).unwrap();
+            assert_eq!(a.x.len(), digits(x));
+            assert!(a.verify::<Sha512>(&commitments, DATA_0).is_ok());
+            assert_eq!(
+                a.verify::<Sha512>(&commitments, DATA_1),
+                Err(Error::InvalidProof)
+            );
+        }
+    }
+    #[test]
+    fn prove_errors() {
+        let rng = &mut OsRng;
+        assert_eq!(
+            OneOfMany::prove::<Sha512>(rng, &[], 0, &BLINDING, DATA_0),
+            Err(Error::EmptyRing)
+        );
+        assert_eq!(
+            OneOfMany::prove::<Sha512>(rng, &COMMITMENTS, X, &BLINDING, DATA_0),
+            Err(Error::SignerIndexOutOfRange)
+        );
+        assert_eq!(
+            OneOfMany::prove::<Sha512>(rng, &COMMITMENTS, 0, &BLINDING, DATA_0),
+            Err(Error::SignerKeyMismatch)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let rng = &mut OsRng;
+        let a = OneOfMany::prove::<Sha512>(rng, &COMMITMENTS, X / 2, &BLINDING, DATA_0).unwrap();
+        let mut commitments = COMMITMENTS.clone();
+        commitments[X / 2] = point_random(rng);
+        assert_eq!(
+            a.verify::<Sha512>(&commitments, DATA_0),
+            Err(Error::InvalidProof)
+        );
+        let mut b = a.clone();
+        b.f[0].push([0; 32]);
+        assert_eq!(
+            b.verify::<Sha512>(&COMMITMENTS, DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut c = a.clone();
+        c.x[0] = [0xff; 32];
+        assert_eq!(
+            c.verify::<Sha512>(&COMMITMENTS, DATA_0),
+            Err(Error::InvalidProofPoint)
+        );
+        let mut d = a;
+        d.z = [0xff; 32];
+        assert_eq!(
+            d.verify::<Sha512>(&COMMITMENTS, DATA_0),
+            Err(Error::NonCanonicalScalar { index: 2 })
+        );
+    }
+    #[test]
+    fn verify_batch() {
+        let rng = &mut OsRng;
+        let a = OneOfMany::prove::<Sha512>(rng, &COMMITMENTS, X / 2, &BLINDING, DATA_0).unwrap();
+        let b = OneOfMany::prove::<Sha512>(rng, &COMMITMENTS, X / 2, &BLINDING, DATA_1).unwrap();
+        let mut c = b.clone();
+        c.z_a = [0xff; 32];
+        assert!(OneOfMany::verify_batch::<Sha512>(
+            rng,
+            &[(&a, &COMMITMENTS, DATA_0), (&b, &COMMITMENTS, DATA_1)]
+        )
+        .is_ok());
+        assert_eq!(
+            OneOfMany::verify_batch::<Sha512>(
+                rng,
+                &[
+                    (&a, &COMMITMENTS, DATA_0),
+                    (&b, &COMMITMENTS, DATA_0),
+                    (&c, &COMMITMENTS, DATA_1),
+                    (&a, &COMMITMENTS, DATA_0),
+                ]
+            ),
+            Err(vec![
+                (1, Error::InvalidProof),
+                (2, Error::NonCanonicalScalar { index: 0 })
+            ])
+        );
+    }
+    #[test]
+    fn serde() {
+        let rng = &mut OsRng;
+        let a = OneOfMany::prove::<Sha512>(rng, &COMMITMENTS, X / 2, &BLINDING, DATA_0).unwrap();
+        let b: OneOfMany = bincode::deserialize(&bincode::serialize(&a).unwrap()).unwrap();
+        assert_eq!(a, b);
+        assert!(b.verify::<Sha512>(&COMMITMENTS, DATA_0).is_ok());
+    }
+    #[test]
+    fn context() {
+        let rng = &mut OsRng;
+        let a = OneOfMany::prove_with_context::<Sha512>(
+            rng,
+            &COMMITMENTS,
+            X / 2,
+            &BLINDING,
+            b"zudo",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(a
+            .verify_with_context::<Sha512>(&COMMITMENTS, b"zudo", DATA_0)
+            .is_ok());
+        assert_eq!(
+            a.verify::<Sha512>(&COMMITMENTS, DATA_0),
+            Err(Error::InvalidProof)
+        );
+    }
+}
@@ -1,57 +1,119 @@
-use crate::point_from_slice;
-use crate::scalar_from_canonical;
-use crate::scalar_from_hash;
-use crate::scalar_random;
-use crate::scalar_zero;
-use crate::RISTRETTO_BASEPOINT_POINT;
-use curve25519_dalek::ristretto::RistrettoPoint;
-use curve25519_dalek::traits::MultiscalarMul;
-use curve25519_dalek::Scalar;
-use digest::typenum::U64;
-use digest::Digest;
+use crate::keys::Ring;
+use crate::keys::SecretKey;
+use crate::legacy_version;
+use crate::members;
+use crate::members::Members;
+use crate::supported_version;
+use crate::Error;
+use crate::Group;
+use crate::RingHash;
+use crate::Ristretto255;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
 use rand::Rng;
 use rand_core::CryptoRngCore;
 use serde::Deserialize;
 use serde::Serialize;
+use std::marker::PhantomData;
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct SAG {
+#[serde(bound = "")]
+pub struct SAG<G: Group = Ristretto255> {
     pub challenge: [u8; 32],
     pub response: Vec<[u8; 32]>,
-    pub ring: Vec<[u8; 32]>,
+    pub ring: Ring<G>,
+    pub version: Version,
 }
 impl SAG {
-    pub fn sign<Hash: Digest<OutputSize = U64> + Clone>(
+    pub fn legacy(challenge: [u8; 32], response: Vec<[u8; 32]>, ring: Ring) -> SAG {
+        SAG {
+            challenge,
+            response,
+            ring,
+            version: Version::Legacy,
+        }
+    }
+}
+impl<G: Group> SAG<G> {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        data: impl AsRef<[u8]>,
+    ) -> Result<SAG<G>, Error> {
+        SAG::sign_with_context::<Hash>(rng, secret, ring, b"", data)
+    }
+    pub fn sign_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<SAG<G>, Error> {
+        SAG::sign_with_version::<Hash>(rng, secret, ring, Version::V2, context, data)
+    }
+    pub fn sign_with_version<Hash: RingHash>(
         rng: &mut impl CryptoRngCore,
-        secret: &Scalar,
-        mut ring: Vec<RistrettoPoint>,
+        secret: &SecretKey<G>,
+        mut ring: Ring<G>,
+        version: Version,
+        context: &[u8],
         data: impl AsRef<[u8]>,
-    ) -> Option<SAG> {
+    ) -> Result<SAG<G>, Error> {
         let secret_index = rng.gen_range(0..=ring.len());
-        ring.insert(secret_index, secret * RISTRETTO_BASEPOINT_POINT);
+        ring.0.insert(secret_index, secret.public_key());
+        SAG::sign_with_index::<Hash>(rng, secret, ring, secret_index, version, context, data)
+    }
+    pub fn sign_canonical<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        mut ring: Ring<G>,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<SAG<G>, Error> {
+        let public_key = secret.public_key();
+        ring.0.push(public_key);
+        ring.canonicalize();
+        ring.validate()?;
+        let secret_index = ring.iter().position(|key| *key == public_key).unwrap();
+        SAG::sign_with_index::<Hash>(rng, secret, ring, secret_index, Version::V2, context, data)
+    }
+    pub fn sign_with_index<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey<G>,
+        ring: Ring<G>,
+        secret_index: usize,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<SAG<G>, Error> {
+        supported_version(version)?;
+        if secret_index >= ring.len() {
+            return Err(Error::SignerIndexOutOfRange);
+        }
+        if ring[secret_index] != secret.public_key() {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let members = Members::new(ring.chunks(1))?;
         let x = ring.len();
-        let hash = Hash::new().chain_update(data);
-        let mut hashes = (0..x).map(|_| hash.clone()).collect::<Vec<_>>();
+        let transcript = SAG::<G>::transcript::<Hash>(version, context, &members.bytes, data)?;
+        let mut transcripts = (0..x).map(|_| transcript.clone()).collect::<Vec<_>>();
         let mut current_index = (secret_index + 1) % x;
-        let secret_scalar_1 = scalar_random(rng);
-        hashes[current_index].update(
-            (secret_scalar_1 * RISTRETTO_BASEPOINT_POINT)
-                .compress()
-                .as_bytes(),
-        );
-        let mut challenges = vec![scalar_zero(); x];
-        challenges[current_index] = scalar_from_hash(hashes[current_index].clone());
-        let mut response = (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+        let secret_scalar_1 = Zeroizing::new(G::scalar_random(rng));
+        transcripts[current_index].append_element::<G>(b"L", &(G::generator() * *secret_scalar_1));
+        let mut challenges = vec![G::scalar_from_u64(0); x];
+        challenges[current_index] = transcripts[current_index].challenge::<G>(b"c");
+        let mut response = (0..x).map(|_| G::scalar_random(rng)).collect::<Vec<_>>();
         loop {
             let next_index = (current_index + 1) % x;
-            hashes[next_index].update(
-                RistrettoPoint::multiscalar_mul(
+            transcripts[next_index].append_element::<G>(
+                b"L",
+                &G::multiscalar_mul(
                     &[response[current_index], challenges[current_index]],
-                    &[RISTRETTO_BASEPOINT_POINT, ring[current_index]],
-                )
-                .compress()
-                .as_bytes(),
+                    &[G::generator(), ring[current_index].0],
+                ),
             );
-            challenges[next_index] = scalar_from_hash(hashes[next_index].clone());
+            challenges[next_index] = transcripts[next_index].challenge::<G>(b"c");
             if (secret_index >= 1 && current_index == (secret_index - 1) % x)
                 || (secret_index == 0 && current_index == x - 1)
             {
@@ -59,64 +121,202 @@ impl SAG {
             }
             current_index = next_index;
         }
-        response[secret_index] = secret_scalar_1 - (challenges[secret_index] * secret);
-        Some(SAG {
-            challenge: challenges[0].to_bytes(),
-            response: response.iter().map(|scalar| scalar.to_bytes()).collect(),
-            ring: ring
-                .iter()
-                .map(|point| point.compress().to_bytes())
-                .collect(),
+        response[secret_index] =
+            *secret_scalar_1 - *Zeroizing::new(challenges[secret_index] * secret.0);
+        Ok(SAG {
+            challenge: G::scalar_to_bytes(&challenges[0]),
+            response: response.iter().map(G::scalar_to_bytes).collect(),
+            ring,
+            version,
+        })
+    }
+    pub fn verify<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(b"", data)
+    }
+    pub fn verify_legacy<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        legacy_version(self.version)?;
+        SAG::verify_transcript::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.ring.chunks(1))?,
+            self.version,
+            b"",
+            data,
+        )
+    }
+    pub fn verify_canonical<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if !self.ring.is_canonical() {
+            return Err(Error::NonCanonicalRing);
+        }
+        self.ring.validate()?;
+        self.verify_with_context::<Hash>(context, data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        SAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(self.ring.chunks(1))?,
+            self.version,
+            context,
+            data,
+        )
+    }
+    pub fn verify_batch<Hash: RingHash>(
+        batch: &[(&SAG<G>, &[u8])],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        SAG::verify_batch_with_context::<Hash>(batch, b"")
+    }
+    pub fn verify_batch_with_context<Hash: RingHash>(
+        batch: &[(&SAG<G>, &[u8])],
+        context: &[u8],
+    ) -> Result<(), Vec<(usize, Error)>> {
+        members::verify_each(batch, |sag, data| {
+            sag.verify_with_context::<Hash>(context, data)
         })
     }
-    pub fn verify<Hash: Digest<OutputSize = U64> + Clone>(&self, data: impl AsRef<[u8]>) -> bool {
-        match || -> Option<bool> {
-            let hash = Hash::new().chain_update(data);
-            let challenge_0 = scalar_from_canonical(self.challenge)?;
-            let mut challenge_1 = challenge_0;
-            let response = self
-                .response
-                .iter()
-                .map(|&bytes| scalar_from_canonical(bytes))
-                .collect::<Option<Vec<_>>>()?;
-            let ring = self
-                .ring
-                .iter()
-                .map(|bytes| point_from_slice(bytes))
-                .collect::<Option<Vec<_>>>()?;
-            for i in 0..self.ring.len() {
-                let mut hash = hash.clone();
-                hash.update(
-                    RistrettoPoint::multiscalar_mul(
-                        &[response[i], challenge_1],
-                        &[RISTRETTO_BASEPOINT_POINT, ring[i]],
-                    )
-                    .compress()
-                    .as_bytes(),
-                );
-                challenge_1 = scalar_from_hash(hash);
+    pub fn detach(self) -> (DetachedSAG<G>, Ring<G>) {
+        (
+            DetachedSAG {
+                challenge: self.challenge,
+                response: self.response,
+                version: self.version,
+                group: PhantomData,
+            },
+            self.ring,
+        )
+    }
+    fn verify_parts<Hash: RingHash>(
+        challenge: [u8; 32],
+        response: &[[u8; 32]],
+        ring: &Members<G>,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        supported_version(version)?;
+        SAG::verify_transcript::<Hash>(challenge, response, ring, version, context, data)
+    }
+    fn verify_transcript<Hash: RingHash>(
+        challenge: [u8; 32],
+        response: &[[u8; 32]],
+        ring: &Members<G>,
+        version: Version,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if response.len() != ring.len() {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let transcript = SAG::<G>::transcript::<Hash>(version, context, &ring.bytes, data)?;
+        let challenge_0 =
+            G::scalar_from_canonical(challenge).ok_or(Error::NonCanonicalChallenge)?;
+        let mut challenge_1 = challenge_0;
+        let response = response
+            .iter()
+            .enumerate()
+            .map(|(index, &bytes)| {
+                G::scalar_from_canonical(bytes).ok_or(Error::NonCanonicalScalar { index })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (response, key) in response.iter().zip(&ring.points) {
+            let mut transcript = transcript.clone();
+            transcript.append_element::<G>(
+                b"L",
+                &G::vartime_multiscalar_mul(&[*response, challenge_1], &[G::generator(), *key]),
+            );
+            challenge_1 = transcript.challenge::<G>(b"c");
+        }
+        if challenge_0 != challenge_1 {
+            return Err(Error::ChallengeMismatch);
+        }
+        Ok(())
+    }
+    fn transcript<Hash: RingHash>(
+        version: Version,
+        context: &[u8],
+        ring: &[G::Encoding],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript = Transcript::versioned(version, b"ring-signature/SAG/v2", context)?;
+        if version != Version::Legacy {
+            for key in ring {
+                transcript.append_encoding::<G>(b"ring", key);
             }
-            Some(challenge_0 == challenge_1)
-        }() {
-            Some(x) => x,
-            None => false,
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+}
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct DetachedSAG<G: Group = Ristretto255> {
+    pub challenge: [u8; 32],
+    pub response: Vec<[u8; 32]>,
+    pub version: Version,
+    #[serde(skip)]
+    group: PhantomData<G>,
+}
+impl<G: Group> DetachedSAG<G> {
+    pub fn attach(self, ring: Ring<G>) -> SAG<G> {
+        SAG {
+            challenge: self.challenge,
+            response: self.response,
+            ring,
+            version: self.version,
         }
     }
+    pub fn verify_with_ring<Hash: RingHash>(
+        &self,
+        ring: &Ring<G>,
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        self.verify_with_ring_and_context::<Hash>(ring, b"", data)
+    }
+    pub fn verify_with_ring_and_context<Hash: RingHash>(
+        &self,
+        ring: &Ring<G>,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        SAG::verify_parts::<Hash>(
+            self.challenge,
+            &self.response,
+            &Members::new(ring.chunks(1))?,
+            self.version,
+            context,
+            data,
+        )
+    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::point_random;
+    use crate::RistrettoPoint;
+    use crate::Wide;
+    use crate::Xof;
     use lazy_static::lazy_static;
     use rand_core::OsRng;
     use sha2::Sha512;
+    use sha3::Keccak256;
+    use sha3::Shake256;
     const DATA: &[u8] = b"hello from zudo";
     const X: usize = 2;
+    const LEGACY: &str = "e1f8902614a17411dbbefe0b5e32ccc37d8a65be6f4b763958ccfde98e7805020200000000000000832c51958ee32cada9b76077dcb1368131f16bb6abc304b3a7db7ff92233b50a2259f816726d2b65522f4319e546a963f23e5f13a814cd808d4e5ee1d08b43030200000000000000fad889239a9916519e4e2cebf7d01df8b4531582431f1585b2d7f3f2ebb1c379d813b6bd665f873584618ef61ad6eb210588bd98abb0b2fe3467cff4bc66756c";
+    const V1: &str = "734397f873ebde1ebb77b8d7dc62b846bb0d9625f0f37d822569f0187dbcb40b02000000000000000ec8c58d7726c8604146d673241dedfe3d6df8cf388809cfd9651e37a3e7e0079a311e2c5f272a36419a2668ed56794ebf418b0bdceed41f6a00f65f1e533503020000000000000020ed0e80d00d08d5bbb3500ea679503e0648bbb16bf75b0a8427f6f793f05f4160eb5a212b1dce855b4d997fb772a787683224049a003e54ff71991b5b5e762a01000000";
     lazy_static! {
-        static ref SECRET_0: Scalar = scalar_random(&mut OsRng);
-        static ref SECRET_1: Scalar = scalar_random(&mut OsRng);
-        static ref RING_0: Vec<RistrettoPoint> = (0..X).map(|_| point_random(&mut OsRng)).collect();
-        static ref RING_1: Vec<RistrettoPoint> = (0..X).map(|_| point_random(&mut OsRng)).collect();
+        static ref SECRET_0: SecretKey = SecretKey::random(&mut OsRng);
+        static ref SECRET_1: SecretKey = SecretKey::random(&mut OsRng);
+        static ref RING_0: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
+        static ref RING_1: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
     }
     #[test]
     fn sign_verify() {
@@ -125,9 +325,297 @@ mod tests {
         let b = SAG::sign::<Sha512>(rng, &SECRET_0, RING_1.clone(), DATA).unwrap();
         let c = SAG::sign::<Sha512>(rng, &SECRET_1, RING_0.clone(), DATA).unwrap();
         let d = SAG::sign::<Sha512>(rng, &SECRET_1, RING_1.clone(), DATA).unwrap();
-        assert!((a.verify::<Sha512>(DATA)));
-        assert!((b.verify::<Sha512>(DATA)));
-        assert!((c.verify::<Sha512>(DATA)));
-        assert!((d.verify::<Sha512>(DATA)));
+        assert!(a.verify::<Sha512>(DATA).is_ok());
+        assert!(b.verify::<Sha512>(DATA).is_ok());
+        assert!(c.verify::<Sha512>(DATA).is_ok());
+        assert!(d.verify::<Sha512>(DATA).is_ok());
+    }
+    #[test]
+    fn sign_with_index() {
+        let rng = &mut OsRng;
+        let mut ring = RING_0.clone();
+        ring.0.insert(1, SECRET_0.public_key());
+        let a =
+            SAG::sign_with_index::<Sha512>(rng, &SECRET_0, ring.clone(), 1, Version::V2, b"", DATA)
+                .unwrap();
+        assert_eq!(a.ring, ring);
+        assert!(a.verify::<Sha512>(DATA).is_ok());
+        assert_eq!(
+            SAG::sign_with_index::<Sha512>(rng, &SECRET_0, ring.clone(), 0, Version::V2, b"", DATA),
+            Err(Error::SignerKeyMismatch)
+        );
+        assert_eq!(
+            SAG::sign_with_index::<Sha512>(rng, &SECRET_0, ring, X + 1, Version::V2, b"", DATA),
+            Err(Error::SignerIndexOutOfRange)
+        );
+    }
+    #[test]
+    fn canonical() {
+        let rng = &mut OsRng;
+        let a = SAG::sign_canonical::<Sha512>(rng, &SECRET_0, RING_0.clone(), b"", DATA).unwrap();
+        assert!(a.ring.is_canonical());
+        assert!(a.verify_canonical::<Sha512>(b"", DATA).is_ok());
+        let mut ring = a.ring.clone();
+        ring.0.reverse();
+        let index = ring
+            .iter()
+            .position(|key| *key == SECRET_0.public_key())
+            .unwrap();
+        let b = SAG::sign_with_index::<Sha512>(rng, &SECRET_0, ring, index, Version::V2, b"", DATA)
+            .unwrap();
+        assert!(b.verify::<Sha512>(DATA).is_ok());
+        assert_eq!(
+            b.verify_canonical::<Sha512>(b"", DATA),
+            Err(Error::NonCanonicalRing)
+        );
+        let mut c = a.clone();
+        c.ring.0[0] = c.ring[1];
+        assert_eq!(
+            c.verify_canonical::<Sha512>(b"", DATA),
+            Err(Error::NonCanonicalRing)
+        );
+        let mut ring = RING_0.clone();
+        ring.0.push(RING_0[0]);
+        ring.0.push(SECRET_0.public_key());
+        assert!(SAG::sign_with_index::<Sha512>(
+            rng,
+            &SECRET_0,
+            ring,
+            X + 1,
+            Version::V2,
+            b"",
+            DATA
+        )
+        .is_ok());
+        let mut ring = RING_0.clone();
+        ring.0.push(RING_0[0]);
+        assert!(matches!(
+            SAG::sign_canonical::<Sha512>(rng, &SECRET_0, ring, b"", DATA),
+            Err(Error::DuplicateMember { .. })
+        ));
+        let mut ring = RING_0.clone();
+        ring.0.push(RistrettoPoint::default().into());
+        assert_eq!(
+            SAG::sign_canonical::<Sha512>(rng, &SECRET_0, ring.clone(), b"", DATA),
+            Err(Error::IdentityMember { row: 0, col: 0 })
+        );
+        ring.0.push(SECRET_0.public_key());
+        ring.canonicalize();
+        let index = ring
+            .iter()
+            .position(|key| *key == SECRET_0.public_key())
+            .unwrap();
+        let d = SAG::sign_with_index::<Sha512>(rng, &SECRET_0, ring, index, Version::V2, b"", DATA)
+            .unwrap();
+        assert!(d.verify::<Sha512>(DATA).is_ok());
+        assert_eq!(
+            d.verify_canonical::<Sha512>(b"", DATA),
+            Err(Error::IdentityMember { row: 0, col: 0 })
+        );
+    }
+    #[test]
+    fn detach() {
+        let rng = &mut OsRng;
+        let a = SAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA).unwrap();
+        let (b, ring) = a.clone().detach();
+        assert!(b.verify_with_ring::<Sha512>(&ring, DATA).is_ok());
+        assert!(bincode::serialize(&b).unwrap().len() < bincode::serialize(&a).unwrap().len());
+        let mut reversed = ring.clone();
+        reversed.0.reverse();
+        assert_eq!(
+            b.verify_with_ring::<Sha512>(&reversed, DATA),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(b.attach(ring), a);
+    }
+    #[test]
+    fn verify_batch() {
+        let rng = &mut OsRng;
+        let a = SAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA).unwrap();
+        let b = SAG::sign::<Sha512>(rng, &SECRET_1, RING_0.clone(), DATA).unwrap();
+        let c = SAG::sign::<Sha512>(rng, &SECRET_0, RING_1.clone(), DATA).unwrap();
+        assert!(SAG::verify_batch::<Sha512>(&[(&a, DATA), (&b, DATA), (&c, DATA)]).is_ok());
+        let mut d = b.clone();
+        d.challenge = [0xff; 32];
+        assert_eq!(
+            SAG::verify_batch::<Sha512>(&[(&a, DATA), (&d, DATA), (&c, b"zudo")]),
+            Err(vec![
+                (1, Error::NonCanonicalChallenge),
+                (2, Error::ChallengeMismatch)
+            ])
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let rng = &mut OsRng;
+        let a = SAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA).unwrap();
+        assert_eq!(
+            a.verify::<Sha512>(b"tampered"),
+            Err(Error::ChallengeMismatch)
+        );
+        let mut b = a.clone();
+        b.response.pop();
+        assert_eq!(b.verify::<Sha512>(DATA), Err(Error::ResponseCountMismatch));
+        let mut c = a.clone();
+        c.response[1] = [0xff; 32];
+        assert_eq!(
+            c.verify::<Sha512>(DATA),
+            Err(Error::NonCanonicalScalar { index: 1 })
+        );
+        let d: SAG = SAG {
+            challenge: a.challenge,
+            response: vec![],
+            ring: Ring::default(),
+            version: Version::V2,
+        };
+        assert_eq!(d.verify::<Sha512>(DATA), Err(Error::EmptyRing));
+    }
+    #[test]
+    #[allow(deprecated)]
+    fn legacy() {
+        let (challenge, response, ring) =
+            bincode::deserialize(&hex::decode(LEGACY).unwrap()).unwrap();
+        let a = SAG::legacy(challenge, response, ring);
+        assert_eq!(a.verify::<Sha512>(DATA), Err(Error::UnsupportedVersion));
+        assert!(a.verify_legacy::<Sha512>(DATA).is_ok());
+        let mut b = a.clone();
+        b.version = Version::V1;
+        assert_eq!(
+            b.verify_legacy::<Sha512>(DATA),
+            Err(Error::ChallengeMismatch)
+        );
+        let rng = &mut OsRng;
+        let mut c = SAG::sign::<Sha512>(rng, &SECRET_0, RING_0.clone(), DATA).unwrap();
+        c.version = Version::Legacy;
+        assert_eq!(c.verify::<Sha512>(DATA), Err(Error::UnsupportedVersion));
+        assert_eq!(
+            SAG::verify_batch::<Sha512>(&[(&c, DATA)]),
+            Err(vec![(0, Error::UnsupportedVersion)])
+        );
+        let (detached, ring) = c.clone().detach();
+        assert_eq!(
+            detached.verify_with_ring::<Sha512>(&ring, DATA),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(
+            c.verify_legacy::<Sha512>(DATA),
+            Err(Error::ChallengeMismatch)
+        );
+        for version in [Version::Legacy, Version::V1] {
+            assert_eq!(
+                SAG::sign_with_version::<Sha512>(
+                    rng,
+                    &SECRET_0,
+                    RING_0.clone(),
+                    version,
+                    b"",
+                    DATA
+                ),
+                Err(Error::UnsupportedVersion)
+            );
+        }
+    }
+    #[test]
+    #[allow(deprecated)]
+    fn context() {
+        let a: SAG = bincode::deserialize(&hex::decode(V1).unwrap()).unwrap();
+        assert_eq!(a.version, Version::V1);
+        assert_eq!(a.verify::<Sha512>(DATA), Err(Error::UnsupportedVersion));
+        assert!(a.verify_legacy::<Sha512>(DATA).is_ok());
+        assert_eq!(
+            a.verify_with_context::<Sha512>(b"zudo", DATA),
+            Err(Error::UnsupportedVersion)
+        );
+        let rng = &mut OsRng;
+        let b = SAG::sign_with_context::<Sha512>(rng, &SECRET_0, RING_0.clone(), b"zudo", DATA)
+            .unwrap();
+        assert_eq!(b.version, Version::V2);
+        assert!(b.verify_with_context::<Sha512>(b"zudo", DATA).is_ok());
+        assert_eq!(
+            b.verify_legacy::<Sha512>(DATA),
+            Err(Error::UnsupportedVersion)
+        );
+        assert_eq!(b.verify::<Sha512>(DATA), Err(Error::ChallengeMismatch));
+        assert_eq!(
+            b.verify_with_context::<Sha512>(b"odu", DATA),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn version() {
+        let rng = &mut OsRng;
+        let a = SAG::sign_with_version::<Sha512>(
+            rng,
+            &SECRET_0,
+            RING_0.clone(),
+            Version::V3,
+            b"zudo",
+            DATA,
+        )
+        .unwrap();
+        assert_eq!(a.version, Version::V3);
+        assert!(a.verify_with_context::<Sha512>(b"zudo", DATA).is_ok());
+        let mut b = a.clone();
+        b.version = Version::V2;
+        assert_eq!(
+            b.verify_with_context::<Sha512>(b"zudo", DATA),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn ring_hash() {
+        let rng = &mut OsRng;
+        let a = SAG::sign::<Xof<Shake256>>(rng, &SECRET_0, RING_0.clone(), DATA).unwrap();
+        let b = SAG::sign::<Wide<Keccak256>>(rng, &SECRET_0, RING_0.clone(), DATA).unwrap();
+        assert!(a.verify::<Xof<Shake256>>(DATA).is_ok());
+        assert!(b.verify::<Wide<Keccak256>>(DATA).is_ok());
+        assert_eq!(
+            a.verify::<Wide<Keccak256>>(DATA),
+            Err(Error::ChallengeMismatch)
+        );
+        assert_eq!(a.verify::<Sha512>(DATA), Err(Error::ChallengeMismatch));
+    }
+    fn group<G: Group>() {
+        let rng = &mut OsRng;
+        let secret = SecretKey::<G>::random(rng);
+        let ring = (0..X)
+            .map(|_| SecretKey::<G>::random(rng).public_key())
+            .collect::<Ring<G>>();
+        let a = SAG::sign::<Sha512>(rng, &secret, ring, DATA).unwrap();
+        assert!(a.verify::<Sha512>(DATA).is_ok());
+        assert_eq!(a.verify::<Sha512>(b"zudo"), Err(Error::ChallengeMismatch));
+        let bytes = bincode::serialize(&a).unwrap();
+        assert_eq!(bincode::deserialize::<SAG<G>>(&bytes).unwrap(), a);
+        let (b, ring) = a.clone().detach();
+        assert!(b.verify_with_ring::<Sha512>(&ring, DATA).is_ok());
+        let bytes = bincode::serialize(&b).unwrap();
+        assert_eq!(bincode::deserialize::<DetachedSAG<G>>(&bytes).unwrap(), b);
+        assert_eq!(b.attach(ring), a);
+    }
+    #[test]
+    fn groups() {
+        group::<Ristretto255>();
+        group::<crate::Ed25519>();
+        #[cfg(feature = "secp256k1")]
+        group::<crate::Secp256k1>();
+        #[cfg(feature = "jubjub")]
+        group::<crate::Jubjub>();
+    }
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn secp256k1() {
+        let rng = &mut OsRng;
+        let secret = SecretKey::<crate::Secp256k1>::random(rng);
+        let keys = (0..X)
+            .map(|_| {
+                SecretKey::<crate::Secp256k1>::random(rng)
+                    .public_key()
+                    .to_sec1(false)
+            })
+            .collect::<Vec<_>>();
+        let ring = Ring::from_sec1(&keys).unwrap();
+        let a =
+            SAG::sign_with_version::<Sha512>(rng, &secret, ring, Version::V3, b"", DATA).unwrap();
+        assert!(a.verify::<Sha512>(DATA).is_ok());
     }
 }
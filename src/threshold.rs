@@ -0,0 +1,675 @@
+use crate::members::Members;
+use crate::one_of_many;
+use crate::scalar_from_canonical;
+use crate::scalar_random;
+use crate::scalar_zero;
+use crate::Error;
+use crate::KeyImage;
+use crate::Ring;
+use crate::RingHash;
+use crate::SecretKey;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
+use crate::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::IsIdentity;
+use curve25519_dalek::traits::MultiscalarMul;
+use curve25519_dalek::traits::VartimeMultiscalarMul;
+use curve25519_dalek::Scalar;
+use rand::Rng;
+use rand_core::CryptoRngCore;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashSet;
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Threshold {
+    pub coefficients: Vec<[u8; 32]>,
+    pub response: Vec<[u8; 32]>,
+    pub ring: Ring,
+}
+impl Threshold {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey],
+        ring: Ring,
+        data: impl AsRef<[u8]>,
+    ) -> Result<Threshold, Error> {
+        Threshold::sign_with_context::<Hash>(rng, secrets, ring, b"", data)
+    }
+    pub fn sign_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey],
+        ring: Ring,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Threshold, Error> {
+        let (ring, secrets) = insert(rng, secrets, ring);
+        Threshold::sign_with_indices::<Hash>(rng, &secrets, ring, context, data)
+    }
+    pub fn sign_with_indices<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[(usize, &SecretKey)],
+        ring: Ring,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Threshold, Error> {
+        check(secrets, &ring)?;
+        let members = Members::new(ring.chunks(1))?;
+        let x = ring.len();
+        let signers = signers(secrets, x);
+        let alpha = Zeroizing::new(
+            secrets
+                .iter()
+                .map(|_| scalar_random(rng))
+                .collect::<Vec<_>>(),
+        );
+        let mut challenges = vec![scalar_zero(); x];
+        let mut response = (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+        let mut transcript = Threshold::transcript::<Hash>(context, &members.bytes, data)?;
+        for (index, signer) in signers.iter().enumerate() {
+            let l = match signer {
+                Some(u) => alpha[*u] * RISTRETTO_BASEPOINT_POINT,
+                None => {
+                    challenges[index] = scalar_random(rng);
+                    RistrettoPoint::multiscalar_mul(
+                        [response[index], challenges[index]],
+                        [RISTRETTO_BASEPOINT_POINT, members.points[index]],
+                    )
+                }
+            };
+            transcript.append_point(b"L", &l);
+        }
+        let challenge = transcript.challenge_scalar(b"c");
+        let coefficients = interpolate(
+            &[(scalar_zero(), challenge)]
+                .into_iter()
+                .chain(
+                    signers
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, signer)| signer.is_none())
+                        .map(|(index, _)| (abscissa(index), challenges[index])),
+                )
+                .collect::<Vec<_>>(),
+        );
+        for (u, &(index, secret)) in secrets.iter().enumerate() {
+            let challenge = one_of_many::poly_eval(&coefficients, abscissa(index));
+            response[index] = alpha[u] - *Zeroizing::new(challenge * secret.0);
+        }
+        Ok(Threshold {
+            coefficients: coefficients.iter().map(Scalar::to_bytes).collect(),
+            response: response.iter().map(Scalar::to_bytes).collect(),
+            ring,
+        })
+    }
+    pub fn threshold(&self) -> usize {
+        (self.ring.len() + 1).saturating_sub(self.coefficients.len())
+    }
+    pub fn verify<Hash: RingHash>(&self, t: usize, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(t, b"", data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        t: usize,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        let members = Members::new(self.ring.chunks(1))?;
+        if self.response.len() != self.ring.len()
+            || self.coefficients.is_empty()
+            || self.coefficients.len() > self.ring.len()
+        {
+            return Err(Error::ResponseCountMismatch);
+        }
+        if t == 0 || self.coefficients.len() + t != self.ring.len() + 1 {
+            return Err(Error::ThresholdMismatch);
+        }
+        let coefficients = self
+            .coefficients
+            .iter()
+            .map(|&bytes| scalar_from_canonical(bytes).ok_or(Error::NonCanonicalChallenge))
+            .collect::<Result<Vec<_>, _>>()?;
+        let response = responses(&self.response)?;
+        let mut transcript = Threshold::transcript::<Hash>(context, &members.bytes, data)?;
+        for (index, (response, key)) in response.iter().zip(&members.points).enumerate() {
+            transcript.append_point(
+                b"L",
+                &RistrettoPoint::vartime_multiscalar_mul(
+                    [
+                        *response,
+                        one_of_many::poly_eval(&coefficients, abscissa(index)),
+                    ],
+                    [RISTRETTO_BASEPOINT_POINT, *key],
+                ),
+            );
+        }
+        if transcript.challenge_scalar(b"c") != coefficients[0] {
+            return Err(Error::ChallengeMismatch);
+        }
+        Ok(())
+    }
+    fn transcript<Hash: RingHash>(
+        context: &[u8],
+        ring: &[[u8; 32]],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript =
+            Transcript::versioned(Version::V2, b"ring-signature/Threshold/v2", context)?;
+        for key in ring {
+            transcript.append_message(b"ring", key);
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+}
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LinkableThreshold {
+    pub challenges: Vec<Vec<[u8; 32]>>,
+    pub response: Vec<Vec<[u8; 32]>>,
+    pub ring: Ring,
+    pub images: Vec<KeyImage>,
+}
+impl LinkableThreshold {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey],
+        ring: Ring,
+        data: impl AsRef<[u8]>,
+    ) -> Result<LinkableThreshold, Error> {
+        LinkableThreshold::sign_with_context::<Hash>(rng, secrets, ring, b"", data)
+    }
+    pub fn sign_with_context<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[SecretKey],
+        ring: Ring,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<LinkableThreshold, Error> {
+        let (ring, secrets) = insert(rng, secrets, ring);
+        LinkableThreshold::sign_with_indices::<Hash>(rng, &secrets, ring, context, data)
+    }
+    pub fn sign_with_indices<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secrets: &[(usize, &SecretKey)],
+        ring: Ring,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<LinkableThreshold, Error> {
+        check(secrets, &ring)?;
+        let members = Members::new(ring.chunks(1))?.hash::<Hash>(Version::V2, 1);
+        let x = ring.len();
+        let images = secrets
+            .iter()
+            .map(|&(index, secret)| KeyImage::from(secret.0 * members.hashes(index)[0]))
+            .collect::<Vec<_>>();
+        let alpha = Zeroizing::new(
+            secrets
+                .iter()
+                .map(|_| scalar_random(rng))
+                .collect::<Vec<_>>(),
+        );
+        let mut challenges = secrets
+            .iter()
+            .map(|_| (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut response = secrets
+            .iter()
+            .map(|_| (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut transcript =
+            LinkableThreshold::transcript::<Hash>(context, &members.bytes, &images, data)?;
+        for (u, &(secret_index, _)) in secrets.iter().enumerate() {
+            for index in 0..x {
+                let hash = members.hashes(index)[0];
+                let (l, r) = if index == secret_index {
+                    (alpha[u] * RISTRETTO_BASEPOINT_POINT, alpha[u] * hash)
+                } else {
+                    (
+                        RistrettoPoint::multiscalar_mul(
+                            [response[u][index], challenges[u][index]],
+                            [RISTRETTO_BASEPOINT_POINT, members.points[index]],
+                        ),
+                        RistrettoPoint::multiscalar_mul(
+                            [response[u][index], challenges[u][index]],
+                            [hash, images[u].0],
+                        ),
+                    )
+                };
+                transcript.append_point(b"L", &l);
+                transcript.append_point(b"R", &r);
+            }
+        }
+        let challenge = transcript.challenge_scalar(b"c");
+        for (u, &(index, secret)) in secrets.iter().enumerate() {
+            challenges[u][index] = challenge
+                - challenges[u]
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != index)
+                    .map(|(_, challenge)| challenge)
+                    .sum::<Scalar>();
+            response[u][index] = alpha[u] - *Zeroizing::new(challenges[u][index] * secret.0);
+        }
+        let to_bytes = |scalars: Vec<Vec<Scalar>>| {
+            scalars
+                .iter()
+                .map(|row| row.iter().map(Scalar::to_bytes).collect())
+                .collect()
+        };
+        Ok(LinkableThreshold {
+            challenges: to_bytes(challenges),
+            response: to_bytes(response),
+            ring,
+            images,
+        })
+    }
+    pub fn threshold(&self) -> usize {
+        self.images.len()
+    }
+    pub fn verify<Hash: RingHash>(&self, t: usize, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.verify_with_context::<Hash>(t, b"", data)
+    }
+    pub fn verify_with_context<Hash: RingHash>(
+        &self,
+        t: usize,
+        context: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<(), Error> {
+        if self.images.is_empty() {
+            return Err(Error::ImageCountMismatch);
+        }
+        if self.images.len() != t {
+            return Err(Error::ThresholdMismatch);
+        }
+        if let Some(index) = self.images.iter().position(|image| image.0.is_identity()) {
+            return Err(Error::InvalidKeyImage { index });
+        }
+        let mut seen = HashSet::new();
+        if !self
+            .images
+            .iter()
+            .all(|image| seen.insert(image.to_bytes()))
+        {
+            return Err(Error::DuplicateImage);
+        }
+        let members = Members::new(self.ring.chunks(1))?.hash::<Hash>(Version::V2, 1);
+        if self.challenges.len() != self.images.len()
+            || self.response.len() != self.images.len()
+            || self
+                .challenges
+                .iter()
+                .chain(&self.response)
+                .any(|row| row.len() != self.ring.len())
+        {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let challenges = self
+            .challenges
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&bytes| scalar_from_canonical(bytes).ok_or(Error::NonCanonicalChallenge))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let response = self
+            .response
+            .iter()
+            .map(|row| responses(row))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut transcript =
+            LinkableThreshold::transcript::<Hash>(context, &members.bytes, &self.images, data)?;
+        for (u, image) in self.images.iter().enumerate() {
+            for (index, key) in members.points.iter().enumerate() {
+                let scalars = [response[u][index], challenges[u][index]];
+                transcript.append_point(
+                    b"L",
+                    &RistrettoPoint::vartime_multiscalar_mul(
+                        scalars,
+                        [RISTRETTO_BASEPOINT_POINT, *key],
+                    ),
+                );
+                transcript.append_point(
+                    b"R",
+                    &RistrettoPoint::vartime_multiscalar_mul(
+                        scalars,
+                        [members.hashes(index)[0], image.0],
+                    ),
+                );
+            }
+        }
+        let challenge = transcript.challenge_scalar(b"c");
+        if challenges
+            .iter()
+            .any(|row| row.iter().sum::<Scalar>() != challenge)
+        {
+            return Err(Error::ChallengeMismatch);
+        }
+        Ok(())
+    }
+    pub fn link(&self, other: &LinkableThreshold) -> bool {
+        self.images.iter().any(|image| other.images.contains(image))
+    }
+    fn transcript<Hash: RingHash>(
+        context: &[u8],
+        ring: &[[u8; 32]],
+        images: &[KeyImage],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript =
+            Transcript::versioned(Version::V2, b"ring-signature/LinkableThreshold/v2", context)?;
+        for key in ring {
+            transcript.append_message(b"ring", key);
+        }
+        for image in images {
+            transcript.append_message(b"image", image.to_bytes());
+        }
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+}
+fn insert<'a>(
+    rng: &mut impl CryptoRngCore,
+    secrets: &'a [SecretKey],
+    mut ring: Ring,
+) -> (Ring, Vec<(usize, &'a SecretKey)>) {
+    for secret in secrets {
+        let index = rng.gen_range(0..=ring.len());
+        ring.0.insert(index, secret.public_key());
+    }
+    let secrets = secrets
+        .iter()
+        .map(|secret| {
+            let public_key = secret.public_key();
+            let index = ring.iter().position(|key| *key == public_key).unwrap();
+            (index, secret)
+        })
+        .collect();
+    (ring, secrets)
+}
+fn check(secrets: &[(usize, &SecretKey)], ring: &Ring) -> Result<(), Error> {
+    if secrets.is_empty() {
+        return Err(Error::EmptySecrets);
+    }
+    let mut seen = HashSet::new();
+    for &(index, secret) in secrets {
+        if index >= ring.len() {
+            return Err(Error::SignerIndexOutOfRange);
+        }
+        if ring[index] != secret.public_key() {
+            return Err(Error::SignerKeyMismatch);
+        }
+        if !seen.insert(index) {
+            return Err(Error::DuplicateMember { row: index });
+        }
+    }
+    Ok(())
+}
+fn signers(secrets: &[(usize, &SecretKey)], x: usize) -> Vec<Option<usize>> {
+    let mut signers = vec![None; x];
+    for (u, &(index, _)) in secrets.iter().enumerate() {
+        signers[index] = Some(u);
+    }
+    signers
+}
+fn responses(response: &[[u8; 32]]) -> Result<Vec<Scalar>, Error> {
+    response
+        .iter()
+        .enumerate()
+        .map(|(index, &bytes)| {
+            scalar_from_canonical(bytes).ok_or(Error::NonCanonicalScalar { index })
+        })
+        .collect()
+}
+fn abscissa(index: usize) -> Scalar {
+    Scalar::from(index as u64 + 1)
+}
+fn interpolate(points: &[(Scalar, Scalar)]) -> Vec<Scalar> {
+    let mut coefficients = vec![scalar_zero(); points.len()];
+    for (i, (x_i, y_i)) in points.iter().enumerate() {
+        let (basis, denominator) = points.iter().enumerate().filter(|(j, _)| *j != i).fold(
+            (vec![Scalar::ONE], Scalar::ONE),
+            |(basis, denominator), (_, (x_j, _))| {
+                (
+                    one_of_many::poly_mul(&basis, &[-x_j, Scalar::ONE]),
+                    denominator * (x_i - x_j),
+                )
+            },
+        );
+        let scale = y_i * denominator.invert();
+        for (coefficient, basis) in coefficients.iter_mut().zip(basis) {
+            *coefficient += scale * basis;
+        }
+    }
+    coefficients
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blsag::BLSAG;
+    use crate::point_random;
+    use lazy_static::lazy_static;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    const DATA_0: &[u8] = b"hello from";
+    const DATA_1: &str = "zudo";
+    const X: usize = 4;
+    lazy_static! {
+        static ref SECRETS: Vec<SecretKey> =
+            (0..3).map(|_| SecretKey::random(&mut OsRng)).collect();
+        static ref RING_0: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
+        static ref RING_1: Ring = (0..X).map(|_| point_random(&mut OsRng)).collect();
+    }
+    #[test]
+    fn interpolate() {
+        let rng = &mut OsRng;
+        let points = (0..5)
+            .map(|_| (scalar_random(rng), scalar_random(rng)))
+            .collect::<Vec<_>>();
+        let coefficients = super::interpolate(&points);
+        assert_eq!(coefficients.len(), 5);
+        for (x, y) in points {
+            assert_eq!(one_of_many::poly_eval(&coefficients, x), y);
+        }
+    }
+    #[test]
+    fn sign_verify() {
+        let rng = &mut OsRng;
+        for (x, t) in [(0, 1), (0, 3), (1, 1), (2, 2), (4, 3), (7, 2)] {
+            let ring = (0..x).map(|_| point_random(rng)).collect();
+            let a = Threshold::sign::<Sha512>(rng, &SECRETS[..t], ring, DATA_0).unwrap();
+            assert_eq!(a.threshold(), t);
+            assert!(a.verify::<Sha512>(t, DATA_0).is_ok());
+            assert_eq!(a.verify::<Sha512>(t, DATA_1), Err(Error::ChallengeMismatch));
+        }
+    }
+    #[test]
+    fn sign_errors() {
+        let rng = &mut OsRng;
+        let mut ring = RING_0.clone();
+        ring.0.insert(1, SECRETS[0].public_key());
+        ring.0.insert(3, SECRETS[1].public_key());
+        let a = Threshold::sign_with_indices::<Sha512>(
+            rng,
+            &[(1, &SECRETS[0]), (3, &SECRETS[1])],
+            ring.clone(),
+            b"",
+            DATA_0,
+        )
+        .unwrap();
+        assert_eq!(a.threshold(), 2);
+        assert!(a.verify::<Sha512>(2, DATA_0).is_ok());
+        assert_eq!(
+            Threshold::sign_with_indices::<Sha512>(rng, &[], ring.clone(), b"", DATA_0),
+            Err(Error::EmptySecrets)
+        );
+        assert_eq!(
+            Threshold::sign_with_indices::<Sha512>(
+                rng,
+                &[(1, &SECRETS[0]), (1, &SECRETS[0])],
+                ring.clone(),
+                b"",
+                DATA_0
+            ),
+            Err(Error::DuplicateMember { row: 1 })
+        );
+        assert_eq!(
+            Threshold::sign_with_indices::<Sha512>(
+                rng,
+                &[(0, &SECRETS[0])],
+                ring.clone(),
+                b"",
+                DATA_0
+            ),
+            Err(Error::SignerKeyMismatch)
+        );
+        assert_eq!(
+            LinkableThreshold::sign_with_indices::<Sha512>(
+                rng,
+                &[(X + 2, &SECRETS[0])],
+                ring,
+                b"",
+                DATA_0
+            ),
+            Err(Error::SignerIndexOutOfRange)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let rng = &mut OsRng;
+        let a = Threshold::sign::<Sha512>(rng, &SECRETS[..2], RING_0.clone(), DATA_0).unwrap();
+        let mut b = a.clone();
+        b.coefficients.remove(1);
+        assert_eq!(b.threshold(), 3);
+        assert_eq!(b.verify::<Sha512>(2, DATA_0), Err(Error::ThresholdMismatch));
+        assert_eq!(b.verify::<Sha512>(3, DATA_0), Err(Error::ChallengeMismatch));
+        let mut c = a.clone();
+        c.response.pop();
+        assert_eq!(
+            c.verify::<Sha512>(2, DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut d = a.clone();
+        d.coefficients.clear();
+        assert_eq!(
+            d.verify::<Sha512>(2, DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut e = a.clone();
+        e.response[2] = [0xff; 32];
+        assert_eq!(
+            e.verify::<Sha512>(2, DATA_0),
+            Err(Error::NonCanonicalScalar { index: 2 })
+        );
+        let mut f = a;
+        f.ring.0.swap(0, 1);
+        assert_eq!(f.verify::<Sha512>(2, DATA_0), Err(Error::ChallengeMismatch));
+    }
+    #[test]
+    fn threshold() {
+        let rng = &mut OsRng;
+        let a = Threshold::sign::<Sha512>(rng, &SECRETS[..1], RING_0.clone(), DATA_0).unwrap();
+        assert!(a.verify::<Sha512>(1, DATA_0).is_ok());
+        assert_eq!(a.verify::<Sha512>(2, DATA_0), Err(Error::ThresholdMismatch));
+        assert_eq!(a.verify::<Sha512>(0, DATA_0), Err(Error::ThresholdMismatch));
+        let b =
+            LinkableThreshold::sign::<Sha512>(rng, &SECRETS[..1], RING_0.clone(), DATA_0).unwrap();
+        assert!(b.verify::<Sha512>(1, DATA_0).is_ok());
+        assert_eq!(b.verify::<Sha512>(2, DATA_0), Err(Error::ThresholdMismatch));
+    }
+    #[test]
+    fn linkable_sign_verify() {
+        let rng = &mut OsRng;
+        for (x, t) in [(0, 1), (0, 2), (2, 1), (4, 3)] {
+            let ring = (0..x).map(|_| point_random(rng)).collect();
+            let a = LinkableThreshold::sign::<Sha512>(rng, &SECRETS[..t], ring, DATA_0).unwrap();
+            assert_eq!(a.threshold(), t);
+            assert!(a.verify::<Sha512>(t, DATA_0).is_ok());
+            assert_eq!(a.verify::<Sha512>(t, DATA_1), Err(Error::ChallengeMismatch));
+        }
+    }
+    #[test]
+    fn linkable_verify_errors() {
+        let rng = &mut OsRng;
+        let a =
+            LinkableThreshold::sign::<Sha512>(rng, &SECRETS[..2], RING_0.clone(), DATA_0).unwrap();
+        let mut b = a.clone();
+        b.images[1] = b.images[0];
+        assert_eq!(b.verify::<Sha512>(2, DATA_0), Err(Error::DuplicateImage));
+        let mut c = a.clone();
+        c.images[0] = SECRETS[2].image::<Sha512>();
+        assert_eq!(c.verify::<Sha512>(2, DATA_0), Err(Error::ChallengeMismatch));
+        let mut d = a.clone();
+        d.images.pop();
+        assert_eq!(d.verify::<Sha512>(2, DATA_0), Err(Error::ThresholdMismatch));
+        assert_eq!(
+            d.verify::<Sha512>(1, DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut e = a.clone();
+        e.images[1] = KeyImage::from(RistrettoPoint::default());
+        assert_eq!(
+            e.verify::<Sha512>(2, DATA_0),
+            Err(Error::InvalidKeyImage { index: 1 })
+        );
+        let mut f = a;
+        f.challenges[1][0] = [0xff; 32];
+        assert_eq!(
+            f.verify::<Sha512>(2, DATA_0),
+            Err(Error::NonCanonicalChallenge)
+        );
+    }
+    #[test]
+    fn link() {
+        let rng = &mut OsRng;
+        let a =
+            LinkableThreshold::sign::<Sha512>(rng, &SECRETS[..2], RING_0.clone(), DATA_0).unwrap();
+        let b =
+            LinkableThreshold::sign::<Sha512>(rng, &SECRETS[1..], RING_1.clone(), DATA_1).unwrap();
+        let c =
+            LinkableThreshold::sign::<Sha512>(rng, &SECRETS[2..], RING_0.clone(), DATA_0).unwrap();
+        let d = BLSAG::sign::<Sha512>(rng, &SECRETS[0], RING_1.clone(), DATA_1).unwrap();
+        assert!(a.link(&b));
+        assert!(!a.link(&c));
+        assert_eq!(a.images[0], d.image);
+        assert_eq!(a.images[1], SECRETS[1].image::<Sha512>());
+    }
+    #[test]
+    fn serde() {
+        let rng = &mut OsRng;
+        let a = Threshold::sign::<Sha512>(rng, &SECRETS[..2], RING_0.clone(), DATA_0).unwrap();
+        let b: Threshold = bincode::deserialize(&bincode::serialize(&a).unwrap()).unwrap();
+        assert_eq!(a, b);
+        assert!(b.verify::<Sha512>(2, DATA_0).is_ok());
+        let c =
+            LinkableThreshold::sign::<Sha512>(rng, &SECRETS[..2], RING_0.clone(), DATA_0).unwrap();
+        let d: LinkableThreshold = bincode::deserialize(&bincode::serialize(&c).unwrap()).unwrap();
+        assert_eq!(c, d);
+        assert!(d.verify::<Sha512>(2, DATA_0).is_ok());
+    }
+    #[test]
+    fn context() {
+        let rng = &mut OsRng;
+        let a = Threshold::sign_with_context::<Sha512>(
+            rng,
+            &SECRETS[..2],
+            RING_0.clone(),
+            b"zudo",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(a.verify_with_context::<Sha512>(2, b"zudo", DATA_0).is_ok());
+        assert_eq!(a.verify::<Sha512>(2, DATA_0), Err(Error::ChallengeMismatch));
+        let b = LinkableThreshold::sign_with_context::<Sha512>(
+            rng,
+            &SECRETS[..2],
+            RING_0.clone(),
+            b"zudo",
+            DATA_0,
+        )
+        .unwrap();
+        assert!(b.verify_with_context::<Sha512>(2, b"zudo", DATA_0).is_ok());
+        assert_eq!(b.verify::<Sha512>(2, DATA_0), Err(Error::ChallengeMismatch));
+    }
+}
@@ -0,0 +1,353 @@
+use crate::members::Members;
+use crate::scalar_from_canonical;
+use crate::scalar_random;
+use crate::Error;
+use crate::PublicKey;
+use crate::Ring;
+use crate::RingHash;
+use crate::SecretKey;
+use crate::Transcript;
+use crate::Version;
+use crate::Zeroizing;
+use crate::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::traits::MultiscalarMul;
+use curve25519_dalek::traits::VartimeMultiscalarMul;
+use curve25519_dalek::Scalar;
+use rand::Rng;
+use rand_core::CryptoRngCore;
+use serde::Deserialize;
+use serde::Serialize;
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Traceable {
+    pub a_1: [u8; 32],
+    pub challenges: Vec<[u8; 32]>,
+    pub response: Vec<[u8; 32]>,
+    pub ring: Ring,
+    pub issue: Vec<u8>,
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Trace {
+    Independent,
+    Linked,
+    Revealed(PublicKey),
+}
+impl Traceable {
+    pub fn sign<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey,
+        mut ring: Ring,
+        issue: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Traceable, Error> {
+        let secret_index = rng.gen_range(0..=ring.len());
+        ring.0.insert(secret_index, secret.public_key());
+        Traceable::sign_with_index::<Hash>(rng, secret, ring, secret_index, issue, data)
+    }
+    pub fn sign_with_index<Hash: RingHash>(
+        rng: &mut impl CryptoRngCore,
+        secret: &SecretKey,
+        ring: Ring,
+        secret_index: usize,
+        issue: &[u8],
+        data: impl AsRef<[u8]>,
+    ) -> Result<Traceable, Error> {
+        if secret_index >= ring.len() {
+            return Err(Error::SignerIndexOutOfRange);
+        }
+        if ring[secret_index] != secret.public_key() {
+            return Err(Error::SignerKeyMismatch);
+        }
+        let members = Members::new(ring.chunks(1))?;
+        let x = ring.len();
+        let data = data.as_ref();
+        let h = Traceable::tag_point::<Hash>(b"h", issue, &members.bytes, b"");
+        let a_0 = Traceable::tag_point::<Hash>(b"A_0", issue, &members.bytes, data);
+        let a_1 = (secret.0 * h - a_0) * abscissa(secret_index).invert();
+        let sigma = sigma(a_0, a_1, x);
+        let alpha = Zeroizing::new(scalar_random(rng));
+        let mut challenges = (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+        let mut response = (0..x).map(|_| scalar_random(rng)).collect::<Vec<_>>();
+        let mut transcript = Traceable::transcript::<Hash>(issue, &members.bytes, a_0, a_1, data)?;
+        for index in 0..x {
+            let (a, b) = if index == secret_index {
+                (*alpha * RISTRETTO_BASEPOINT_POINT, *alpha * h)
+            } else {
+                let scalars = [response[index], challenges[index]];
+                (
+                    RistrettoPoint::multiscalar_mul(
+                        scalars,
+                        [RISTRETTO_BASEPOINT_POINT, members.points[index]],
+                    ),
+                    RistrettoPoint::multiscalar_mul(scalars, [h, sigma[index]]),
+                )
+            };
+            transcript.append_point(b"a", &a);
+            transcript.append_point(b"b", &b);
+        }
+        challenges[secret_index] = transcript.challenge_scalar(b"c")
+            - challenges
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| *index != secret_index)
+                .map(|(_, challenge)| challenge)
+                .sum::<Scalar>();
+        response[secret_index] = *alpha - *Zeroizing::new(challenges[secret_index] * secret.0);
+        Ok(Traceable {
+            a_1: a_1.compress().to_bytes(),
+            challenges: challenges.iter().map(Scalar::to_bytes).collect(),
+            response: response.iter().map(Scalar::to_bytes).collect(),
+            ring,
+            issue: issue.to_vec(),
+        })
+    }
+    pub fn verify<Hash: RingHash>(&self, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.sigma::<Hash>(data.as_ref()).map(|_| ())
+    }
+    fn sigma<Hash: RingHash>(&self, data: &[u8]) -> Result<Vec<RistrettoPoint>, Error> {
+        let members = Members::new(self.ring.chunks(1))?;
+        let x = self.ring.len();
+        if self.challenges.len() != x || self.response.len() != x {
+            return Err(Error::ResponseCountMismatch);
+        }
+        let a_1 = crate::point_from_slice(&self.a_1).ok_or(Error::InvalidProofPoint)?;
+        let challenges = self
+            .challenges
+            .iter()
+            .map(|&bytes| scalar_from_canonical(bytes).ok_or(Error::NonCanonicalChallenge))
+            .collect::<Result<Vec<_>, _>>()?;
+        let response = self
+            .response
+            .iter()
+            .enumerate()
+            .map(|(index, &bytes)| {
+                scalar_from_canonical(bytes).ok_or(Error::NonCanonicalScalar { index })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let h = Traceable::tag_point::<Hash>(b"h", &self.issue, &members.bytes, b"");
+        let a_0 = Traceable::tag_point::<Hash>(b"A_0", &self.issue, &members.bytes, data);
+        let sigma = sigma(a_0, a_1, x);
+        let mut transcript =
+            Traceable::transcript::<Hash>(&self.issue, &members.bytes, a_0, a_1, data)?;
+        for index in 0..x {
+            let scalars = [response[index], challenges[index]];
+            transcript.append_point(
+                b"a",
+                &RistrettoPoint::vartime_multiscalar_mul(
+                    scalars,
+                    [RISTRETTO_BASEPOINT_POINT, members.points[index]],
+                ),
+            );
+            transcript.append_point(
+                b"b",
+                &RistrettoPoint::vartime_multiscalar_mul(scalars, [h, sigma[index]]),
+            );
+        }
+        if transcript.challenge_scalar(b"c") != challenges.iter().sum::<Scalar>() {
+            return Err(Error::ChallengeMismatch);
+        }
+        Ok(sigma)
+    }
+    fn tag_point<Hash: RingHash>(
+        label: &[u8],
+        issue: &[u8],
+        ring: &[[u8; 32]],
+        data: &[u8],
+    ) -> RistrettoPoint {
+        let mut hash = Hash::init()
+            .chain_absorb(b"ring-signature/Traceable/v2")
+            .chain_absorb(label)
+            .chain_absorb((issue.len() as u64).to_le_bytes())
+            .chain_absorb(issue)
+            .chain_absorb((ring.len() as u64).to_le_bytes());
+        for key in ring {
+            hash.absorb(key);
+        }
+        RistrettoPoint::from_uniform_bytes(&hash.chain_absorb(data).finalize_wide())
+    }
+    fn transcript<Hash: RingHash>(
+        issue: &[u8],
+        ring: &[[u8; 32]],
+        a_0: RistrettoPoint,
+        a_1: RistrettoPoint,
+        data: &[u8],
+    ) -> Result<Transcript<Hash>, Error> {
+        let mut transcript =
+            Transcript::versioned(Version::V2, b"ring-signature/Traceable/v2", b"")?;
+        transcript.append_message(b"issue", issue);
+        for key in ring {
+            transcript.append_message(b"ring", key);
+        }
+        transcript.append_point(b"A_0", &a_0);
+        transcript.append_point(b"A_1", &a_1);
+        transcript.append_message(b"data", data);
+        Ok(transcript)
+    }
+}
+pub fn trace<Hash: RingHash>(
+    a: &Traceable,
+    data_a: impl AsRef<[u8]>,
+    b: &Traceable,
+    data_b: impl AsRef<[u8]>,
+) -> Result<Trace, Error> {
+    let data_a = data_a.as_ref();
+    let data_b = data_b.as_ref();
+    let sigma_a = a.sigma::<Hash>(data_a)?;
+    let sigma_b = b.sigma::<Hash>(data_b)?;
+    if a.issue != b.issue || a.ring != b.ring {
+        return Ok(Trace::Independent);
+    }
+    let matches = sigma_a
+        .iter()
+        .zip(&sigma_b)
+        .enumerate()
+        .filter(|(_, (sigma_a, sigma_b))| sigma_a == sigma_b)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    Ok(match matches.len() {
+        1 if data_a != data_b => Trace::Revealed(a.ring[matches[0]]),
+        len if len == sigma_a.len() => Trace::Linked,
+        1 => Trace::Revealed(a.ring[matches[0]]),
+        _ => Trace::Independent,
+    })
+}
+fn sigma(a_0: RistrettoPoint, a_1: RistrettoPoint, x: usize) -> Vec<RistrettoPoint> {
+    (0..x).map(|index| a_0 + abscissa(index) * a_1).collect()
+}
+fn abscissa(index: usize) -> Scalar {
+    Scalar::from(index as u64 + 1)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_random;
+    use lazy_static::lazy_static;
+    use rand_core::OsRng;
+    use sha2::Sha512;
+    const DATA_0: &[u8] = b"hello from";
+    const DATA_1: &str = "zudo";
+    const ISSUE_0: &[u8] = b"2024";
+    const ISSUE_1: &[u8] = b"2028";
+    const X: usize = 4;
+    lazy_static! {
+        static ref SECRET_0: SecretKey = SecretKey::random(&mut OsRng);
+        static ref SECRET_1: SecretKey = SecretKey::random(&mut OsRng);
+        static ref RING: Ring = {
+            let mut ring = (0..X).map(|_| point_random(&mut OsRng)).collect::<Ring>();
+            ring.0.insert(1, SECRET_0.public_key());
+            ring.0.insert(3, SECRET_1.public_key());
+            ring
+        };
+    }
+    fn sign(secret: &SecretKey, issue: &[u8], data: impl AsRef<[u8]>) -> Traceable {
+        let index = RING
+            .iter()
+            .position(|key| *key == secret.public_key())
+            .unwrap();
+        Traceable::sign_with_index::<Sha512>(&mut OsRng, secret, RING.clone(), index, issue, data)
+            .unwrap()
+    }
+    #[test]
+    fn sign_verify() {
+        let rng = &mut OsRng;
+        for x in [0, 1, 2, 5] {
+            let ring = (0..x).map(|_| point_random(rng)).collect();
+            let a = Traceable::sign::<Sha512>(rng, &SECRET_0, ring, ISSUE_0, DATA_0).unwrap();
+            assert!(a.verify::<Sha512>(DATA_0).is_ok());
+            assert_eq!(a.verify::<Sha512>(DATA_1), Err(Error::ChallengeMismatch));
+        }
+        assert_eq!(
+            Traceable::sign_with_index::<Sha512>(rng, &SECRET_0, RING.clone(), 0, ISSUE_0, DATA_0),
+            Err(Error::SignerKeyMismatch)
+        );
+        assert_eq!(
+            Traceable::sign_with_index::<Sha512>(
+                rng,
+                &SECRET_0,
+                RING.clone(),
+                X + 2,
+                ISSUE_0,
+                DATA_0
+            ),
+            Err(Error::SignerIndexOutOfRange)
+        );
+    }
+    #[test]
+    fn verify_errors() {
+        let a = sign(&SECRET_0, ISSUE_0, DATA_0);
+        let mut b = a.clone();
+        b.issue = ISSUE_1.to_vec();
+        assert_eq!(b.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+        let mut c = a.clone();
+        c.a_1 = RistrettoPoint::default().compress().to_bytes();
+        assert_eq!(c.verify::<Sha512>(DATA_0), Err(Error::ChallengeMismatch));
+        let mut d = a.clone();
+        d.a_1 = [0xff; 32];
+        assert_eq!(d.verify::<Sha512>(DATA_0), Err(Error::InvalidProofPoint));
+        let mut e = a.clone();
+        e.challenges.pop();
+        assert_eq!(
+            e.verify::<Sha512>(DATA_0),
+            Err(Error::ResponseCountMismatch)
+        );
+        let mut f = a;
+        f.response[0] = [0xff; 32];
+        assert_eq!(
+            f.verify::<Sha512>(DATA_0),
+            Err(Error::NonCanonicalScalar { index: 0 })
+        );
+    }
+    #[test]
+    fn trace() {
+        let a = sign(&SECRET_0, ISSUE_0, DATA_0);
+        let b = sign(&SECRET_0, ISSUE_0, DATA_1);
+        let c = sign(&SECRET_0, ISSUE_0, DATA_0);
+        let d = sign(&SECRET_1, ISSUE_0, DATA_1);
+        let e = sign(&SECRET_0, ISSUE_1, DATA_1);
+        assert_eq!(
+            super::trace::<Sha512>(&a, DATA_0, &b, DATA_1),
+            Ok(Trace::Revealed(SECRET_0.public_key()))
+        );
+        assert_eq!(
+            super::trace::<Sha512>(&a, DATA_0, &c, DATA_0),
+            Ok(Trace::Linked)
+        );
+        assert_eq!(
+            super::trace::<Sha512>(&a, DATA_0, &d, DATA_1),
+            Ok(Trace::Independent)
+        );
+        assert_eq!(
+            super::trace::<Sha512>(&a, DATA_0, &e, DATA_1),
+            Ok(Trace::Independent)
+        );
+        assert_eq!(
+            super::trace::<Sha512>(&a, DATA_1, &b, DATA_1),
+            Err(Error::ChallengeMismatch)
+        );
+    }
+    #[test]
+    fn trace_single_member() {
+        let rng = &mut OsRng;
+        let a =
+            Traceable::sign::<Sha512>(rng, &SECRET_0, Ring::default(), ISSUE_0, DATA_0).unwrap();
+        let b =
+            Traceable::sign::<Sha512>(rng, &SECRET_0, Ring::default(), ISSUE_0, DATA_1).unwrap();
+        let c =
+            Traceable::sign::<Sha512>(rng, &SECRET_0, Ring::default(), ISSUE_0, DATA_0).unwrap();
+        assert_eq!(
+            super::trace::<Sha512>(&a, DATA_0, &b, DATA_1),
+            Ok(Trace::Revealed(SECRET_0.public_key()))
+        );
+        assert_eq!(
+            super::trace::<Sha512>(&a, DATA_0, &c, DATA_0),
+            Ok(Trace::Linked)
+        );
+    }
+    #[test]
+    fn serde() {
+        let a = sign(&SECRET_1, ISSUE_0, DATA_0);
+        let b: Traceable = bincode::deserialize(&bincode::serialize(&a).unwrap()).unwrap();
+        assert_eq!(a, b);
+        assert!(b.verify::<Sha512>(DATA_0).is_ok());
+    }
+}
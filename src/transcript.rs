@@ -0,0 +1,123 @@
+use crate::Error;
+use crate::Group;
+use crate::RingHash;
+use crate::Ristretto255;
+use crate::Version;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::Scalar;
+#[derive(Clone)]
+pub struct Transcript<Hash> {
+    hash: Hash,
+    labelled: bool,
+    standard: bool,
+}
+impl<Hash: RingHash> Transcript<Hash> {
+    pub fn new(label: &[u8]) -> Transcript<Hash> {
+        let mut transcript = Transcript {
+            hash: Hash::init(),
+            labelled: true,
+            standard: false,
+        };
+        transcript.append_message(b"dom-sep", label);
+        transcript
+    }
+    pub(crate) fn unlabelled() -> Transcript<Hash> {
+        Transcript {
+            hash: Hash::init(),
+            labelled: false,
+            standard: false,
+        }
+    }
+    pub(crate) fn versioned(
+        version: Version,
+        label: &[u8],
+        context: &[u8],
+    ) -> Result<Transcript<Hash>, Error> {
+        match version {
+            Version::V2 => {
+                let mut transcript = Transcript::new(label);
+                transcript.append_message(b"context", context);
+                Ok(transcript)
+            }
+            Version::V3 => {
+                let mut transcript = Transcript::new(label);
+                transcript.standard = true;
+                transcript.append_u64(b"version", 3);
+                transcript.append_message(b"context", context);
+                Ok(transcript)
+            }
+            _ if !context.is_empty() => Err(Error::UnsupportedContext),
+            _ => Ok(Transcript::unlabelled()),
+        }
+    }
+    pub fn append_message(&mut self, label: &[u8], message: impl AsRef<[u8]>) {
+        let message = message.as_ref();
+        if self.labelled {
+            self.hash.absorb(&(label.len() as u64).to_le_bytes());
+            self.hash.absorb(label);
+            self.hash.absorb(&(message.len() as u64).to_le_bytes());
+        }
+        self.hash.absorb(message);
+    }
+    pub fn append_u64(&mut self, label: &[u8], value: u64) {
+        self.append_message(label, value.to_le_bytes());
+    }
+    pub fn append_point(&mut self, label: &[u8], point: &RistrettoPoint) {
+        self.append_element::<Ristretto255>(label, point);
+    }
+    pub fn append_element<G: Group>(&mut self, label: &[u8], point: &G::Point) {
+        self.append_encoding::<G>(label, &G::point_to_bytes(point));
+    }
+    pub fn append_encoding<G: Group>(&mut self, label: &[u8], encoding: &G::Encoding) {
+        match G::coordinates(encoding) {
+            Some(coordinates) => {
+                if self.labelled {
+                    self.hash.absorb(&(label.len() as u64).to_le_bytes());
+                    self.hash.absorb(label);
+                    self.hash.absorb(&64u64.to_le_bytes());
+                }
+                self.hash.absorb_elements(&coordinates);
+            }
+            None => self.append_message(label, encoding),
+        }
+    }
+    pub fn challenge_scalar(&self, label: &[u8]) -> Scalar {
+        self.challenge::<Ristretto255>(label)
+    }
+    pub fn challenge<G: Group>(&self, label: &[u8]) -> G::Scalar {
+        let mut hash = self.hash.clone();
+        if self.labelled {
+            hash.absorb(&(label.len() as u64).to_le_bytes());
+            hash.absorb(label);
+        }
+        if self.standard {
+            G::scalar_from_standard(&hash.finalize_wide())
+        } else {
+            G::scalar_from_hash(hash)
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalar_from_hash;
+    use sha2::Sha512;
+    #[test]
+    fn labels() {
+        let mut a = Transcript::<Sha512>::new(b"a");
+        let mut b = Transcript::<Sha512>::new(b"b");
+        a.append_message(b"x", b"yz");
+        b.append_message(b"x", b"yz");
+        assert_ne!(a.challenge_scalar(b"c"), b.challenge_scalar(b"c"));
+        let mut c = Transcript::<Sha512>::new(b"a");
+        c.append_message(b"xy", b"z");
+        assert_ne!(a.challenge_scalar(b"c"), c.challenge_scalar(b"c"));
+        assert_ne!(a.challenge_scalar(b"c"), a.challenge_scalar(b"d"));
+        let mut d = Transcript::<Sha512>::unlabelled();
+        d.append_message(b"x", b"yz");
+        assert_eq!(
+            d.challenge_scalar(b"c"),
+            scalar_from_hash(Sha512::init().chain_absorb(b"yz"))
+        );
+    }
+}
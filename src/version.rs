@@ -0,0 +1,24 @@
+#![allow(deprecated)]
+use crate::Error;
+use serde::Deserialize;
+use serde::Serialize;
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Version {
+    Legacy,
+    #[deprecated(note = "unlabelled transcript; sign with V2 and verify with verify_legacy")]
+    V1,
+    V2,
+    V3,
+}
+pub(crate) fn supported_version(version: Version) -> Result<(), Error> {
+    match version {
+        Version::V2 | Version::V3 => Ok(()),
+        _ => Err(Error::UnsupportedVersion),
+    }
+}
+pub(crate) fn legacy_version(version: Version) -> Result<(), Error> {
+    match version {
+        Version::V2 | Version::V3 => Err(Error::UnsupportedVersion),
+        _ => Ok(()),
+    }
+}